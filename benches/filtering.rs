@@ -0,0 +1,63 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gimoji::{
+    emoji::{Emoji, Field, EMOJIS},
+    matcher::MatchMode,
+    query::{Query, SearchOptions},
+    synonyms::Synonyms,
+};
+
+/// Filtering all the Unicode emojis, with each match mode and queries of increasing length.
+fn filter(c: &mut Criterion) {
+    let emojis: Vec<&Emoji> = EMOJIS.emojis().iter().collect();
+    for mode in [MatchMode::Substring, MatchMode::Fuzzy, MatchMode::Regex] {
+        let options = SearchOptions {
+            mode,
+            synonyms: Synonyms::builtin(),
+            case_sensitive: false,
+            typo_tolerance: 0,
+            fields: Field::ALL.into(),
+        };
+        let mut group = c.benchmark_group(format!("filter/{mode}"));
+        for text in QUERIES {
+            let query = Query::parse(text, &options).expect("The queries are valid");
+            group.bench_with_input(BenchmarkId::from_parameter(text), &query, |b, query| {
+                b.iter(|| query.filter(&emojis))
+            });
+        }
+        group.finish();
+    }
+}
+
+/// Typing a query one character at a time, refiltering the previous matches each time as the
+/// picker does.
+fn refilter(c: &mut Criterion) {
+    let emojis: Vec<&Emoji> = EMOJIS.emojis().iter().collect();
+    let options = SearchOptions {
+        mode: MatchMode::Substring,
+        synonyms: Synonyms::builtin(),
+        case_sensitive: false,
+        typo_tolerance: 1,
+        fields: Field::ALL.into(),
+    };
+    let text = QUERIES[QUERIES.len() - 1];
+    let queries: Vec<_> = (1..=text.len())
+        .map(|len| Query::parse(&text[..len], &options).expect("The queries are valid"))
+        .collect();
+
+    c.bench_function("refilter/typing", |b| {
+        b.iter(|| {
+            let mut matches = queries[0].filter(&emojis);
+            for pair in queries.windows(2) {
+                pair[1].refilter(&pair[0], &mut matches, &emojis);
+            }
+
+            matches
+        })
+    });
+}
+
+/// Queries of increasing length, and matching fewer and fewer emojis.
+const QUERIES: [&str; 4] = ["a", "fa", "face", "face with"];
+
+criterion_group!(benches, filter, refilter);
+criterion_main!(benches);
@@ -1,44 +1,375 @@
 use std::{
-    env::var_os,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    env::{var, var_os},
     error::Error,
-    fs::{read_to_string, File},
+    fs::{read_dir, read_to_string, File},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process,
 };
 
-use databake::Bake;
+use miniz_oxide::deflate::compress_to_vec;
 use serde::Deserialize;
+use unicode_normalization::is_nfc;
 
-#[derive(Deserialize, Debug, Bake)]
-#[databake(path = gimoji::emoji)]
-pub struct Emoji<'e> {
-    code: &'e str,
-    description: &'e str,
-    emoji: &'e str,
-    entity: &'e str,
-    name: &'e str,
+#[path = "src/normalize.rs"]
+mod normalize;
+
+// Strings with escape sequences can't be borrowed, hence the `Cow`s.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct EmojiEntry<'e> {
+    #[serde(borrow)]
+    code: Cow<'e, str>,
+    #[serde(borrow)]
+    description: Cow<'e, str>,
+    #[serde(borrow)]
+    emoji: Cow<'e, str>,
+    #[serde(borrow)]
+    entity: Cow<'e, str>,
+    #[serde(borrow)]
+    name: Cow<'e, str>,
+    #[serde(borrow, alias = "group")]
+    category: Option<Cow<'e, str>>,
+    semver: Option<Semver>,
+    #[serde(default)]
+    weight: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Semver {
+    Major,
+    Minor,
+    Patch,
 }
 
 #[derive(serde::Deserialize, Debug)]
 pub struct Emojis<'e> {
     #[serde(borrow)]
-    gitmojis: Vec<Emoji<'e>>,
+    gitmojis: Vec<EmojiEntry<'e>>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let path = PathBuf::from(EMOJI_FILE);
-    let emojis_json = read_to_string(path)?;
-    let emojis: Emojis = serde_json::from_str(&emojis_json)?;
-    let baked = emojis.gitmojis[..].bake(&Default::default()).to_string();
+fn main() {
+    // The default error reporting of build scripts only shows the `Debug` representation.
+    if let Err(e) = generate() {
+        eprintln!("error: {e}");
+        process::exit(1);
+    }
+}
 
-    let out = format!("pub const EMOJIS: &[crate::emoji::Emoji] = &{baked};\n");
+fn generate() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed=src/normalize.rs");
+    println!("cargo:rerun-if-env-changed={EMOJIS_JSON_ENV}");
 
-    let out_dir = var_os("OUT_DIR").unwrap();
-    let dest_path = PathBuf::from(out_dir).join("emojis.rs");
-    let mut dest_file = File::create(dest_path)?;
+    let out_dir = PathBuf::from(var_os("OUT_DIR").unwrap());
+    let mut out = String::new();
+    for (i, set) in EMOJI_SETS.iter().enumerate() {
+        // Packagers can bake their own curated emojis in, as the default set.
+        let (source, generated) = match var(EMOJIS_JSON_ENV) {
+            Ok(source) if i == 0 => (source, false),
+            _ => (set.file.to_owned(), set.generated),
+        };
+        out.push_str(&bake_emojis(&source, set.constant, generated, &out_dir)?);
+    }
+
+    let mut dest_file = File::create(out_dir.join("emojis.rs"))?;
     dest_file.write_all(out.as_bytes())?;
 
+    let mut dest_file = File::create(out_dir.join("translations.rs"))?;
+    dest_file.write_all(bake_translations()?.as_bytes())?;
+
     Ok(())
 }
 
-const EMOJI_FILE: &str = "emojis.json";
+/// Generate the `TRANSLATIONS` constant from the `locales/<set>/<language>.json` files, which map
+/// emoji codes to translated descriptions.
+fn bake_translations() -> Result<String, Box<dyn Error>> {
+    println!("cargo:rerun-if-changed={LOCALES_DIR}");
+
+    let mut files = Vec::new();
+    for set_dir in read_dir(LOCALES_DIR)? {
+        let set_dir = set_dir?.path();
+        for file in read_dir(&set_dir)? {
+            files.push(file?.path());
+        }
+    }
+    files.sort();
+
+    let mut translations = String::new();
+    for file in files {
+        let (Some(set), Some(language)) = (
+            file.parent().and_then(|dir| dir.file_name()?.to_str()),
+            file.file_stem().and_then(|stem| stem.to_str()),
+        ) else {
+            continue;
+        };
+        let json = read_to_string(&file)?;
+        let descriptions: BTreeMap<String, String> = serde_json::from_str(&json)
+            .map_err(|e| format!("`{}` is not a valid translation: {e}", file.display()))?;
+
+        let descriptions: Vec<_> = descriptions
+            .iter()
+            .map(|(code, description)| format!("({code:?}, {description:?})"))
+            .collect();
+        translations.push_str(&format!(
+            "crate::locale::Translation {{ set: {set:?}, language: {language:?}, descriptions: &[{}] }},",
+            descriptions.join(", ")
+        ));
+    }
+
+    Ok(format!(
+        "pub const TRANSLATIONS: &[crate::locale::Translation] = &[{translations}];\n"
+    ))
+}
+
+/// Generate the `name` static holding the emojis of the given file or URL, compressed in a file of
+/// the output directory, along with the tables to look them up.
+fn bake_emojis(
+    source: &str,
+    name: &str,
+    generated: bool,
+    out_dir: &Path,
+) -> Result<String, Box<dyn Error>> {
+    let emojis_json = if source.starts_with("https://") || source.starts_with("http://") {
+        fetch(source)?
+    } else {
+        println!("cargo:rerun-if-changed={source}");
+        read_to_string(PathBuf::from(source))
+            .map_err(|e| format!("Failed to read `{source}`: {e}"))?
+    };
+    let mut emojis: Emojis = serde_json::from_str(&emojis_json)
+        .map_err(|e| format!("`{source}` is not a valid gitmoji database: {e}"))?;
+    validate(&emojis, generated)
+        .map_err(|e| format!("`{source}` is not a valid gitmoji database:\n{e}"))?;
+    // The order doesn't depend on the one of the file, so that regenerating it doesn't reorder the
+    // list. Skin tone variants come right after their base emoji, and emojis repeated as is, the
+    // only ones sharing a code, are kept once.
+    emojis.gitmojis.sort_by(|a, b| a.code.cmp(&b.code));
+    emojis.gitmojis.dedup_by(|a, b| a.code == b.code);
+
+    // One record per emoji, with its fields in the order of `gimoji::emoji::Field`, its semver and
+    // weight, and then the normalized, lowercase fields that searches match against, precomputed.
+    // The gitmoji schema has no tags, those can only be added by users.
+    let records: Vec<String> = emojis
+        .gitmojis
+        .iter()
+        .map(|e| {
+            let category = e.category.as_deref().unwrap_or_default();
+            let fields = [
+                &*e.code,
+                &e.description,
+                &e.emoji,
+                &e.entity,
+                &e.name,
+                "",
+                category,
+            ];
+            let semver = e.semver.map(|semver| format!("{semver:?}").to_lowercase());
+            let normalized = fields.map(|field| normalize::normalize(field).to_lowercase());
+
+            fields
+                .map(str::to_owned)
+                .into_iter()
+                .chain([semver.unwrap_or_default(), e.weight.to_string()])
+                .chain(normalized)
+                .collect::<Vec<_>>()
+                .join(FIELD_SEPARATOR)
+        })
+        .collect();
+    let compressed = compress_to_vec(records.join(RECORD_SEPARATOR).as_bytes(), COMPRESSION_LEVEL);
+    let file = format!("{name}.deflate");
+    File::create(out_dir.join(&file))?.write_all(&compressed)?;
+
+    let by_code = lookup_table(emojis.gitmojis.iter().map(|e| &*e.code))?;
+    let by_char = lookup_table(emojis.gitmojis.iter().map(|e| &*e.emoji))?;
+    let code_order = order_table(&emojis.gitmojis, |e| &e.code);
+    let category_order = order_table(&emojis.gitmojis, |e| {
+        e.category.as_deref().unwrap_or_default()
+    });
+
+    Ok(format!(
+        "pub static {name}: crate::emoji::Compressed = \
+         crate::emoji::Compressed::new(include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{file}\")));\n\
+         pub const {name}_BY_CODE: &[(&str, u16)] = &[{by_code}];\n\
+         pub const {name}_BY_CHAR: &[(&str, u16)] = &[{by_char}];\n\
+         pub const {name}_CODE_ORDER: &[u16] = &[{code_order}];\n\
+         pub const {name}_CATEGORY_ORDER: &[u16] = &[{category_order}];\n"
+    ))
+}
+
+/// The indices of the emojis sorted like `gimoji::emoji::Order` sorts them by the given field:
+/// heavier emojis first, then by the normalized, lowercase field, and in the database order.
+///
+/// The emojis were already checked to fit in `u16` indices by the lookup tables.
+fn order_table<'e>(emojis: &'e [EmojiEntry], field: impl Fn(&'e EmojiEntry) -> &'e str) -> String {
+    let mut order: Vec<_> = (0..emojis.len()).collect();
+    order.sort_by_cached_key(|&i| {
+        let e = &emojis[i];
+        (
+            Reverse(e.weight),
+            normalize::normalize(field(e)).to_lowercase(),
+            i,
+        )
+    });
+
+    let entries: Vec<_> = order.iter().map(usize::to_string).collect();
+    entries.join(", ")
+}
+
+#[cfg(feature = "network")]
+fn fetch(url: &str) -> Result<String, Box<dyn Error>> {
+    let json = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Failed to fetch `{url}`: {e}"))?
+        .into_string()
+        .map_err(|e| format!("Failed to read `{url}`: {e}"))?;
+
+    Ok(json)
+}
+
+#[cfg(not(feature = "network"))]
+fn fetch(url: &str) -> Result<String, Box<dyn Error>> {
+    Err(format!("Fetching `{url}` needs the `network` feature").into())
+}
+
+/// The entries of a table of the emojis' indices sorted by the given keys, for binary searches.
+///
+/// Only the first emoji is kept for keys that several have, as custom sets may repeat emojis.
+fn lookup_table<'e>(keys: impl Iterator<Item = &'e str>) -> Result<String, Box<dyn Error>> {
+    let mut table = keys
+        .enumerate()
+        .map(|(i, key)| Ok((key, u16::try_from(i)?)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    table.sort();
+    table.dedup_by_key(|(key, _)| *key);
+
+    let entries: Vec<_> = table
+        .iter()
+        .map(|(key, i)| format!("({key:?}, {i})"))
+        .collect();
+
+    Ok(entries.join(", "))
+}
+
+/// Check the emojis beyond what deserialization does, so that mistakes show up at build time.
+///
+/// All the problems are reported at once, one per line.
+fn validate(emojis: &Emojis, generated: bool) -> Result<(), String> {
+    if emojis.gitmojis.is_empty() {
+        return Err("it has no emojis".to_owned());
+    }
+
+    let mut problems = Vec::new();
+    let mut codes = HashMap::new();
+    let mut chars = HashMap::new();
+    for (i, e) in emojis.gitmojis.iter().enumerate() {
+        let fields = [
+            ("code", &e.code),
+            ("description", &e.description),
+            ("emoji", &e.emoji),
+            ("entity", &e.entity),
+            ("name", &e.name),
+        ];
+        for (field, value) in fields {
+            if value.trim().is_empty() {
+                problems.push(format!("emoji #{i} (`{}`) has an empty {field}", e.code));
+            } else if !is_nfc(value) {
+                problems.push(format!(
+                    "emoji #{i} (`{}`) has a {field} not in Unicode normalization form C",
+                    e.code
+                ));
+            } else if value.chars().any(char::is_control) {
+                problems.push(format!(
+                    "emoji #{i} (`{}`) has a {field} with control characters",
+                    e.code
+                ));
+            }
+        }
+        if e.emoji.chars().any(is_private_use) {
+            problems.push(format!(
+                "emoji #{i} (`{}`) is in the Private Use Area, so it only renders with some fonts",
+                e.code
+            ));
+        }
+        if !(e.code.len() > 2 && e.code.starts_with(':') && e.code.ends_with(':')) {
+            problems.push(format!(
+                "emoji #{i} has the code `{}`, which is not of the form `:code:`",
+                e.code
+            ));
+        }
+
+        // Emojis repeated as is are deduplicated.
+        let first = codes.insert(&e.code, i);
+        if first.is_some_and(|first| emojis.gitmojis[first] == *e) {
+            continue;
+        }
+        if let Some(first) = first {
+            problems.push(format!(
+                "emoji #{i} has the same code as emoji #{first}: `{}`",
+                e.code
+            ));
+        }
+        if generated {
+            if let Some(first) = chars.insert(&e.emoji, i) {
+                problems.push(format!(
+                    "emoji #{i} (`{}`) is the same emoji as #{first}: {}",
+                    e.code, e.emoji
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+/// Whether the character is in one of the Private Use Areas, which fonts fill as they see fit.
+fn is_private_use(c: char) -> bool {
+    matches!(c, '\u{e000}'..='\u{f8ff}' | '\u{f0000}'..='\u{ffffd}' | '\u{100000}'..='\u{10fffd}')
+}
+
+/// An embedded emoji set.
+struct EmbeddedSet {
+    file: &'static str,
+    /// The name of the constant generated for the set.
+    constant: &'static str,
+    /// Whether the set is generated from Unicode data rather than curated.
+    ///
+    /// The emojis of generated sets must all be different, while curated sets can map several codes
+    /// to the same emoji.
+    generated: bool,
+}
+
+/// The separators of the fields of each emoji, and of the emojis, in the compressed files.
+const FIELD_SEPARATOR: &str = "\x1f";
+const RECORD_SEPARATOR: &str = "\x1e";
+const COMPRESSION_LEVEL: u8 = 10;
+const EMOJIS_JSON_ENV: &str = "GIMOJI_EMOJIS_JSON";
+const LOCALES_DIR: &str = "locales";
+const EMOJI_SETS: [EmbeddedSet; 4] = [
+    EmbeddedSet {
+        file: "emojis.json",
+        constant: "EMOJIS",
+        generated: true,
+    },
+    EmbeddedSet {
+        file: "gitmoji.json",
+        constant: "GITMOJI",
+        generated: false,
+    },
+    EmbeddedSet {
+        file: "devmoji.json",
+        constant: "DEVMOJI",
+        generated: false,
+    },
+    EmbeddedSet {
+        file: "conventional.json",
+        constant: "CONVENTIONAL",
+        generated: false,
+    },
+];
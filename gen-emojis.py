@@ -19,15 +19,28 @@ def fetch_openmoji_data(url):
 
 def map_to_schema(emojis_data):
     mapped_emojis = []
+    codes = set()
     for emoji_data in emojis_data:
+        # OpenMoji's own emojis are in the Private Use Area, so they only render with its font.
+        if emoji_data["group"] == "extras-openmoji":
+            continue
+        code = f":{emoji_data['annotation'].replace(' ', '_').lower()}:"
+        if code in codes:
+            logging.warning(f"Skipping duplicate code {code}")
+            continue
+        codes.add(code)
+
         mapped_emojis.append({
             "emoji": emoji_data["emoji"],
             "entity": f"&#x{emoji_data['hexcode']};",
-            "code": f":{emoji_data['annotation'].replace(' ', '_').lower()}:",
+            "code": code,
             "description": emoji_data["annotation"],
             "name": emoji_data["annotation"].replace(' ', '_').lower(),
+            "category": emoji_data["group"],
             "semver": None
         })
+    # Sorted like the build sorts them, so that regenerating the file only shows actual changes.
+    mapped_emojis.sort(key=lambda emoji: emoji["code"])
     return mapped_emojis
 
 def save_to_json(mapped_emojis, output_file):
@@ -35,7 +48,8 @@ def save_to_json(mapped_emojis, output_file):
         json.dump({
             "$schema": "https://gitmoji.dev/api/gitmojis/schema",
             "gitmojis": mapped_emojis
-        }, f, ensure_ascii=False, indent=4)
+        }, f, ensure_ascii=False, indent=2)
+        f.write("\n")
     logging.info(f"Emojis saved to {output_file}")
 
 if __name__ == "__main__":
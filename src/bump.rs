@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+use crate::{emoji::Emoji, emoji::Semver, git, hook, Error};
+
+/// A commit calling for a version bump, as told by the semver of its emoji.
+#[derive(Serialize)]
+pub struct Commit {
+    /// The abbreviated hash of the commit.
+    pub hash: String,
+    pub subject: String,
+    pub semver: Semver,
+}
+
+/// The version bump the commits since the last tag call for.
+#[derive(Serialize)]
+pub struct Recommendation {
+    /// The last tag, none if there are none yet, in which case all the commits count.
+    pub since: Option<String>,
+    /// The biggest bump called for, none if no commit calls for one.
+    pub bump: Option<Semver>,
+    /// The commits calling for a bump, newest first.
+    pub commits: Vec<Commit>,
+}
+
+impl Recommendation {
+    /// The commits calling for the recommended bump.
+    pub fn driving_commits(&self) -> impl Iterator<Item = &Commit> {
+        self.commits
+            .iter()
+            .filter(|commit| Some(commit.semver) == self.bump)
+    }
+}
+
+/// Recommend a version bump from the emojis of the commits since the last tag, as reachable from
+/// `HEAD`, in the repository of the current directory.
+///
+/// Fails with [`Error::NotAGitRepo`] outside of a repository.
+pub fn recommend(emojis: &[&'static Emoji]) -> anyhow::Result<Recommendation> {
+    if git::repo_root().is_none() {
+        return Err(Error::NotAGitRepo.into());
+    }
+
+    let since = git::last_tag();
+    let commits: Vec<_> = git::log_subjects(since.as_deref())
+        .into_iter()
+        .filter_map(|(hash, subject)| {
+            let text = hook::find_emoji(&subject, emojis)?;
+            let emoji = emojis
+                .iter()
+                .find(|emoji| emoji.emoji == text || emoji.code == text)?;
+            let semver = emoji.semver?;
+
+            Some(Commit {
+                hash,
+                subject,
+                semver,
+            })
+        })
+        .collect();
+    // Major bumps being the first semver.
+    let bump = commits.iter().map(|commit| commit.semver).min();
+
+    Ok(Recommendation {
+        since,
+        bump,
+        commits,
+    })
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+#[cfg(feature = "network")]
+use crate::{database, http};
+
+/// The outcome of [`update`].
+pub enum Update {
+    UpToDate,
+    Updated,
+}
+
+/// The upstream gitmoji database as last fetched by [`update`], if it's in the cache.
+///
+/// Caches written by a version of gimoji with a different cache format are ignored.
+pub fn gitmojis() -> Option<String> {
+    let dir = paths::cache_dir()?;
+    read_info(&dir)?;
+
+    fs::read_to_string(dir.join(DATABASE_FILE)).ok()
+}
+
+/// Fetch the gitmoji database from the given URL, gitmoji.dev's by default, into the cache, unless
+/// the cached one is up to date.
+///
+/// An interrupted download is resumed by the next update.
+#[cfg(feature = "network")]
+pub fn update(url: Option<&str>) -> anyhow::Result<Update> {
+    let url = url.unwrap_or(GITMOJI_URL);
+    let dir = paths::cache_dir().context("Failed to find the cache directory")?;
+    // Only trust the ETag if the database it's for is still around, and from the same place.
+    let etag = gitmojis()
+        .and_then(|_| read_info(&dir))
+        .filter(|info| info.url.as_deref().unwrap_or(GITMOJI_URL) == url)
+        .and_then(|info| info.etag);
+
+    fs::create_dir_all(&dir).context("Failed to create the cache directory")?;
+    let path = dir.join(DATABASE_FILE);
+    let (json, etag) = match http::download(url, etag.as_deref(), &path)? {
+        http::Download::NotModified => return Ok(Update::UpToDate),
+        http::Download::Downloaded { body, etag } => (body, etag),
+    };
+
+    let info = CacheInfo {
+        version: CACHE_VERSION,
+        etag,
+        url: Some(url.to_owned()),
+    };
+    database::validate(&json).context("Fetched an invalid gitmoji database")?;
+    write(&path, &json)?;
+    let info = serde_json::to_string(&info).expect("Cache info is always serializable");
+    write(&dir.join(INFO_FILE), &info)?;
+
+    Ok(Update::Updated)
+}
+
+#[cfg(not(feature = "network"))]
+pub fn update(_url: Option<&str>) -> anyhow::Result<Update> {
+    anyhow::bail!("gimoji was built without network support, the database can't be updated")
+}
+
+/// The luma of the terminal's background, as detected less than [`LUMA_TTL`] ago in this terminal,
+/// if it was.
+///
+/// Terminals are told apart by their `TERM` and, under X11, `WINDOWID`.
+pub fn luma() -> Option<f32> {
+    let json = fs::read_to_string(paths::cache_dir()?.join(LUMA_FILE)).ok()?;
+    let lumas: BTreeMap<String, CachedLuma> = serde_json::from_str(&json).ok()?;
+
+    lumas
+        .get(&terminal_key())
+        .filter(|cached| now().saturating_sub(cached.timestamp) < LUMA_TTL.as_secs())
+        .map(|cached| cached.luma)
+}
+
+/// Cache the luma detected in this terminal, dropping the ones that expired.
+pub fn set_luma(luma: f32) -> anyhow::Result<()> {
+    let dir = paths::cache_dir().context("Failed to find the cache directory")?;
+    let path = dir.join(LUMA_FILE);
+    let mut lumas: BTreeMap<String, CachedLuma> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let now = now();
+    lumas.retain(|_, cached| now.saturating_sub(cached.timestamp) < LUMA_TTL.as_secs());
+    lumas.insert(
+        terminal_key(),
+        CachedLuma {
+            luma,
+            timestamp: now,
+        },
+    );
+
+    fs::create_dir_all(&dir).context("Failed to create the cache directory")?;
+    let json = serde_json::to_string(&lumas).expect("Cached lumas are always serializable");
+    write(&path, &json)
+}
+
+/// A detected luma of a terminal's background.
+#[derive(Serialize, Deserialize)]
+struct CachedLuma {
+    luma: f32,
+    /// When it was detected, in seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+/// What tells this terminal apart from others, which may have other backgrounds.
+fn terminal_key() -> String {
+    let var = |name| env::var(name).unwrap_or_default();
+
+    format!("{}:{}", var("TERM"), var("WINDOWID"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// What's known about the cached database.
+#[derive(Serialize, Deserialize)]
+struct CacheInfo {
+    /// The format of the cache, [`CACHE_VERSION`] for the current one.
+    version: u32,
+    /// The ETag of the database, to only download it again when it changed.
+    etag: Option<String>,
+    /// Where the database was fetched from, gitmoji.dev if unknown.
+    #[serde(default)]
+    url: Option<String>,
+}
+
+/// The info of the cache in the given directory, if it's in the current format.
+fn read_info(dir: &Path) -> Option<CacheInfo> {
+    let json = fs::read_to_string(dir.join(INFO_FILE)).ok()?;
+    let info: CacheInfo = serde_json::from_str(&json).ok()?;
+
+    (info.version == CACHE_VERSION).then_some(info)
+}
+
+/// Write the file through a temporary one, so that readers never see it half-written.
+fn write(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).with_context(|| format!("Failed to write `{}`", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+#[cfg(feature = "network")]
+const GITMOJI_URL: &str = "https://gitmoji.dev/api/gitmojis";
+const CACHE_VERSION: u32 = 1;
+const DATABASE_FILE: &str = "gitmojis.json";
+const INFO_FILE: &str = "gitmojis.info.json";
+const LUMA_FILE: &str = "luma.json";
+/// How long detected lumas are trusted, short as terminals may switch themes.
+pub const LUMA_TTL: Duration = Duration::from_secs(10 * 60);
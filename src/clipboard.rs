@@ -0,0 +1,259 @@
+use std::{fs, io::Write, process, time::Duration};
+
+use anyhow::{bail, Context};
+#[cfg(feature = "clipboard")]
+use arboard::Clipboard;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// How `copy` reaches the clipboard.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardBackend {
+    /// The `clipboard-command` if set, the Windows clipboard in WSL, through the terminal in SSH
+    /// sessions, where the system clipboard is out of reach, and directly otherwise.
+    #[default]
+    Auto,
+    /// The clipboard of the X11 or Wayland session, or of the OS.
+    System,
+    /// The terminal's clipboard, set with an OSC 52 escape sequence, which the terminal has to
+    /// support.
+    Osc52,
+    /// The `clipboard-command`, e.g. `wl-copy` or `xclip -selection clipboard`.
+    Command,
+    /// The Windows clipboard from WSL, where users paste rather than in the Linux one, through
+    /// `clip.exe`.
+    Wsl,
+}
+
+impl ClipboardBackend {
+    /// The backend to use, this one unless it's `auto`, given whether a `clipboard-command` is set.
+    pub fn resolve(self, has_command: bool) -> Self {
+        match self {
+            Self::Auto if has_command => Self::Command,
+            Self::Auto if is_wsl() => Self::Wsl,
+            Self::Auto if is_ssh() => Self::Osc52,
+            Self::Auto => Self::System,
+            backend => backend,
+        }
+    }
+}
+
+/// Which of the clipboards of X11 and Wayland `copy` sets.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardSelection {
+    /// The regular clipboard, pasted with Ctrl+V.
+    #[default]
+    Clipboard,
+    /// The primary selection, pasted with a middle click.
+    Primary,
+    /// Both of them.
+    Both,
+}
+
+/// Whether we're running in the Windows Subsystem for Linux, from the variable it sets or else the
+/// kernel it runs.
+fn is_wsl() -> bool {
+    if std::env::var_os("WSL_DISTRO_NAME").is_some_and(|name| !name.is_empty()) {
+        return true;
+    }
+
+    cfg!(target_os = "linux")
+        && fs::read_to_string("/proc/version")
+            .is_ok_and(|version| version.to_lowercase().contains("microsoft"))
+}
+
+/// Whether we're running in tmux, from the variable it sets.
+pub fn is_tmux() -> bool {
+    std::env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty())
+}
+
+/// Whether we're running in an SSH session, from the variable OpenSSH sets for interactive ones.
+fn is_ssh() -> bool {
+    std::env::var_os("SSH_TTY").is_some_and(|tty| !tty.is_empty())
+}
+
+/// Whether the system clipboard can be accessed, warning about it otherwise, e.g. on a headless
+/// server.
+///
+/// Checked before daemonizing to hold the clipboard, after which errors can't be reported anymore.
+#[cfg(feature = "clipboard")]
+pub fn is_available() -> bool {
+    match Clipboard::new() {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("WARNING: Failed to access the clipboard: {e}. Printing the emoji instead.");
+            false
+        }
+    }
+}
+
+/// Never, without the `clipboard` feature.
+#[cfg(not(feature = "clipboard"))]
+pub fn is_available() -> bool {
+    eprintln!(
+        "WARNING: gimoji was built without system clipboard support. Printing the emoji instead."
+    );
+    false
+}
+
+/// Copy the text to the clipboard by running the command with the shell, with the text on its
+/// standard input.
+pub fn copy_with_command(text: &str, command: &str) -> anyhow::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+
+    pipe_to(shell, command, text.as_bytes())
+}
+
+/// Load the text into a new tmux paste buffer.
+pub fn copy_to_tmux_buffer(text: &str) -> anyhow::Result<()> {
+    let status = process::Command::new("tmux")
+        .args(["set-buffer", "--", text])
+        .status()
+        .context("Failed to run `tmux`")?;
+    if !status.success() {
+        bail!("`tmux set-buffer` exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// Copy the text to the Windows clipboard from WSL, with `clip.exe`.
+pub fn copy_to_windows_clipboard(text: &str) -> anyhow::Result<()> {
+    // `clip.exe` reads text in the console's code page unless it starts with a BOM, so UTF-16 with
+    // one gets emojis through intact.
+    let utf16: Vec<_> = "\u{feff}"
+        .encode_utf16()
+        .chain(text.encode_utf16())
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    pipe_to(process::Command::new(CLIP_EXE), CLIP_EXE, &utf16)
+}
+
+/// Run the command, named as given in errors, with the input on its standard input.
+fn pipe_to(mut command: process::Command, name: &str, input: &[u8]) -> anyhow::Result<()> {
+    let mut child = command
+        .stdin(process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{name}`"))?;
+    let mut stdin = child.stdin.take().expect("The standard input is piped");
+    stdin
+        .write_all(input)
+        .with_context(|| format!("Failed to write to `{name}`"))?;
+    // Closed so the command sees the end of the input.
+    drop(stdin);
+
+    let status = child
+        .wait()
+        .with_context(|| format!("Failed to run `{name}`"))?;
+    if !status.success() {
+        bail!("Failed to copy emoji to clipboard: `{name}` exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// Copy the text to the clipboard.
+///
+/// This function exits the process and never returns because on some platforms (X11, Wayland)
+/// clipboard data is only available for as long as the process that "owns" it is alive, in which
+/// case this function will spawn a background task to host the clipboard data, until another
+/// selection replaces it or the timeout if any. With `wait`, the clipboard data is hosted in the
+/// foreground instead.
+///
+/// Note that it is possible to make it work without exiting the process, but it would require an
+/// `unsafe { fork() }`. However, in this program this is simply not needed.
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(
+    emoji: &str,
+    selection: ClipboardSelection,
+    timeout: Option<Duration>,
+    wait: bool,
+) -> anyhow::Result<()> {
+    macro_rules! clipboard {
+        () => {
+            Clipboard::new().map_err(|e| Error::ClipboardUnavailable(e.to_string()))?
+        };
+    }
+
+    macro_rules! paste_text {
+        ($set:expr) => {
+            $set.text(emoji)
+                .context("Failed to copy emoji to clipboard")?
+        };
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "illumos",
+            target_os = "linux",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "solaris"
+        ))]
+        {
+            use arboard::{LinuxClipboardKind, SetExtLinux};
+            use std::time::Instant;
+
+            if wait {
+                println!(
+                    "Holding the clipboard until something else is copied, press Ctrl+C to stop"
+                );
+            } else {
+                nix::unistd::daemon(false, false).context("Failed to daemonize process")?;
+            }
+            let mut clipboard = clipboard!();
+            if selection == ClipboardSelection::Both {
+                // Held along with the clipboard, until that one is replaced.
+                paste_text!(clipboard.set().clipboard(LinuxClipboardKind::Primary))
+            }
+            let kind = match selection {
+                ClipboardSelection::Primary => LinuxClipboardKind::Primary,
+                _ => LinuxClipboardKind::Clipboard,
+            };
+            let set = clipboard.set().clipboard(kind);
+            // arboard answers the paste requests without telling about them, so the clipboard can
+            // be held until a timeout but not until the first paste.
+            paste_text!(match timeout {
+                Some(timeout) => set.wait_until(Instant::now() + timeout),
+                None => set.wait(),
+            })
+        } else {
+            // Only X11 and Wayland have a primary selection, and clipboards to hold.
+            let _ = (selection, timeout, wait);
+            paste_text!(clipboard!().set())
+        }
+    }
+
+    process::exit(0)
+}
+
+/// Fails, without the `clipboard` feature.
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(
+    _emoji: &str,
+    _selection: ClipboardSelection,
+    _timeout: Option<Duration>,
+    _wait: bool,
+) -> anyhow::Result<()> {
+    let reason = "gimoji was built without system clipboard support";
+
+    Err(Error::ClipboardUnavailable(reason.to_owned()).into())
+}
+
+const CLIP_EXE: &str = "clip.exe";
@@ -0,0 +1,85 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListState, Padding, StatefulWidget, Widget},
+};
+
+use crate::{colors::Colors, emoji::Emoji};
+
+/// The commits to pick an emoji for, one after the other, listed beside the picker with the emojis
+/// picked so far.
+pub struct CommitList {
+    commits: Box<[String]>,
+    emojis: Vec<Option<&'static Emoji>>,
+    current: usize,
+    colors: Colors,
+}
+
+impl CommitList {
+    /// The commits are expected to be non-empty.
+    pub fn new(colors: Colors, commits: Box<[String]>) -> Self {
+        Self {
+            emojis: vec![None; commits.len()],
+            commits,
+            current: 0,
+            colors,
+        }
+    }
+
+    /// Give the current commit the emoji and move on to the next one, returning whether there's
+    /// one left.
+    pub fn pick(&mut self, emoji: &'static Emoji) -> bool {
+        self.emojis[self.current] = Some(emoji);
+        self.current += 1;
+
+        self.current < self.commits.len()
+    }
+
+    /// The emojis picked for the commits, in order, none for the ones left.
+    pub fn into_emojis(self) -> Vec<Option<&'static Emoji>> {
+        self.emojis
+    }
+}
+
+impl Widget for &CommitList {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let items = self
+            .commits
+            .iter()
+            .zip(&self.emojis)
+            .map(|(commit, emoji)| {
+                let emoji = emoji.map_or(NO_EMOJI, |emoji| emoji.emoji);
+                Line::raw(format!("{emoji} {commit}"))
+            });
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "{TITLE} ({}/{})",
+                        self.current + 1,
+                        self.commits.len()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.colors.border))
+                    .padding(Padding::new(1, 1, 1, 0)),
+            )
+            .style(Style::default().fg(self.colors.unselected))
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(self.colors.selected),
+            )
+            .highlight_symbol(HIGHLIGHT_SYMBOL);
+        // Scrolled for the current commit to be in view.
+        let mut state = ListState::default().with_selected(Some(self.current));
+
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+const TITLE: &str = "Commits";
+const HIGHLIGHT_SYMBOL: &str = "> ";
+// As wide as an emoji.
+const NO_EMOJI: &str = "  ";
@@ -0,0 +1,1660 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::ErrorKind,
+    ops::Range,
+    path::{Path, PathBuf},
+    slice,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    forward_to_deserialize_any, Deserialize, Deserializer, Serialize,
+};
+
+use ratatui::style::Color;
+
+use crate::{
+    clipboard::{ClipboardBackend, ClipboardSelection},
+    colors::Colors,
+    database::REPO_DATABASE_GIT_CONFIG,
+    emoji::{EmojiFormat, Field, Order},
+    git,
+    gitmojirc::GitmojiRc,
+    history::Preselect,
+    hook::{Existing, MessageSource, Position, ShowMessage},
+    keys::{Action, Chords, Keymap},
+    matcher::MatchMode,
+    paths,
+    picker::Picker,
+    suggest::Rule,
+    ColorScheme, OutputFormat,
+};
+
+/// Settings from one of the places they can be set, e.g. a config file.
+///
+/// Everything is optional, the unset settings being taken from the next place in the order of
+/// precedence, see [`Settings`](crate::settings::Settings).
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub color_scheme: Option<ColorScheme>,
+    /// Whether the color scheme is detected from the terminal's background color when it's not
+    /// set, which requires probing the terminal.
+    pub detect_color_scheme: Option<bool>,
+    /// The color scheme used when it's neither set nor detected.
+    pub default_color_scheme: Option<ColorScheme>,
+    /// The emoji set selected by default.
+    pub set: Option<String>,
+    /// The language of the emoji descriptions, e.g. `fr`, the one of the locale if not set.
+    pub language: Option<String>,
+    pub order: Option<Order>,
+    /// How emojis are selected: `tui`, `fzf`, or `dmenu[:<command>]` for a launcher like rofi.
+    pub picker: Option<Picker>,
+    /// The emoji of the history to take without showing the picker, if any.
+    pub preselect: Option<Preselect>,
+    /// How the selected emoji is copied or inserted in commit messages.
+    pub emoji_format: Option<EmojiFormat>,
+    /// The format of the `search` results.
+    pub output_format: Option<OutputFormat>,
+    /// Whether `copy` puts the selected emoji in the clipboard, rather than printing it.
+    pub clipboard: Option<bool>,
+    /// How `copy` puts the selected emoji on the clipboard, the general `emoji-format` if not set.
+    pub copy_format: Option<EmojiFormat>,
+    pub clipboard_backend: Option<ClipboardBackend>,
+    /// The command that copies its standard input to the clipboard, for the `command` backend.
+    pub clipboard_command: Option<String>,
+    pub clipboard_selection: Option<ClipboardSelection>,
+    /// How many seconds `copy` holds the clipboard in the background with X11 and Wayland, until
+    /// it's replaced if not set.
+    pub clipboard_timeout: Option<u64>,
+    /// Whether `copy` also loads the selected emoji into a tmux paste buffer, when running in tmux.
+    pub tmux_buffer: Option<bool>,
+    pub colors: ColorsConfig,
+    pub keys: KeysConfig,
+    pub hook: HookConfig,
+    pub lint: LintConfig,
+    pub search: SearchConfig,
+    pub database: DatabaseConfig,
+    pub history: HistoryConfig,
+    pub suggest: SuggestConfig,
+    /// The profile selected on the command line, whose `[profile.<name>]` settings apply.
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// The names of the profiles defined in this place.
+    #[serde(skip)]
+    pub profiles: Vec<String>,
+    /// Whether invalid settings are errors rather than ignored with a warning, as set on the
+    /// command line.
+    #[serde(skip)]
+    pub strict: bool,
+}
+
+impl Config {
+    /// The settings from all the places they can be set, from the lowest precedence to the highest.
+    ///
+    /// The profile is the one selected on the command line, or through `GIMOJI_PROFILE`. The
+    /// settings of a place with invalid ones are ignored with a warning, unless `strict` is set on
+    /// the command line, in which case they're an error.
+    pub fn layers(cli: Self) -> anyhow::Result<[(Origin, Self); 7]> {
+        let profile = cli.profile.clone().or_else(|| env::var(PROFILE_ENV).ok());
+        let profile = profile.as_deref();
+        let strict = cli.strict;
+        let checked = |config: anyhow::Result<Self>| match config {
+            Err(e) if !strict => {
+                eprintln!(
+                    "WARNING: {}. Ignoring these settings.",
+                    format!("{e:#}").trim_end()
+                );
+                Ok(Self::default())
+            }
+            config => config,
+        };
+        let gitmojirc = GitmojiRc::load().map(|rc| Self {
+            emoji_format: rc.emoji_format,
+            ..Self::default()
+        });
+
+        let layers = [
+            (Origin::Default, Self::defaults()),
+            (Origin::GitmojiRc, checked(gitmojirc)?),
+            (Origin::User, checked(Self::user(profile))?),
+            (Origin::Git, checked(Self::git(profile))?),
+            (Origin::Repo, checked(Self::repo(profile))?),
+            (Origin::Env, checked(Self::env())?),
+            (Origin::Cli, cli),
+        ];
+        if let Some(profile) = profile {
+            let mut names: Vec<_> = layers
+                .iter()
+                .flat_map(|(_, config)| config.profiles.iter().map(String::as_str))
+                .collect();
+            if !names.contains(&profile) {
+                names.sort_unstable();
+                names.dedup();
+                if names.is_empty() {
+                    bail!("Unknown profile `{profile}`, no profiles are defined");
+                }
+                bail!(
+                    "Unknown profile `{profile}`, available profiles are: {}",
+                    names.join(", ")
+                );
+            }
+        }
+
+        Ok(layers)
+    }
+
+    /// The defaults of the settings that have a fixed one.
+    ///
+    /// The others are left unset, e.g. the color scheme which is detected from the terminal.
+    pub fn defaults() -> Self {
+        Self {
+            detect_color_scheme: Some(true),
+            default_color_scheme: Some(ColorScheme::default()),
+            order: Some(Order::default()),
+            picker: Some(Picker::default()),
+            emoji_format: Some(EmojiFormat::default()),
+            output_format: Some(OutputFormat::default()),
+            clipboard: Some(true),
+            // Falls back to the general `emoji-format`.
+            copy_format: None,
+            clipboard_backend: Some(ClipboardBackend::default()),
+            clipboard_selection: Some(ClipboardSelection::default()),
+            tmux_buffer: Some(false),
+            colors: ColorsConfig {
+                light: ThemeConfig::from(Colors::LIGHT),
+                dark: ThemeConfig::from(Colors::DARK),
+            },
+            keys: KeysConfig::from(Keymap::default()),
+            hook: HookConfig {
+                migrate_codes: Some(false),
+                position: Some(Position::default()),
+                // Falls back to the general `emoji-format`.
+                emoji_format: None,
+                sources: Some(DEFAULT_HOOK_SOURCES.into()),
+                existing: Some(Existing::default()),
+                skip_in_ci: Some(false),
+                skip_without_tty: Some(false),
+                show_message: Some(ShowMessage::default()),
+            },
+            lint: LintConfig {
+                require_emoji: Some(true),
+                // Anywhere.
+                position: None,
+                // All the emojis of the set.
+                allowed: None,
+                // Unlimited.
+                max_emojis: None,
+                match_commit_type: Some(false),
+            },
+            search: SearchConfig {
+                match_mode: Some(MatchMode::default()),
+                case_sensitive: Some(false),
+                typo_tolerance: Some(DEFAULT_TYPO_TOLERANCE),
+                fields: Some(Field::ALL.into()),
+                synonyms: Some(Vec::new()),
+            },
+            database: DatabaseConfig {
+                // All of them, the first one being the default `set`.
+                sets: None,
+                files: Some(Vec::new()),
+                hide: Some(Vec::new()),
+                gitmojis: Some(Vec::new()),
+            },
+            history: HistoryConfig {
+                enabled: Some(true),
+                // The data directory.
+                dir: None,
+                max_entries: Some(DEFAULT_HISTORY_MAX_ENTRIES),
+                // Forever.
+                max_age_days: None,
+                remember_query: Some(false),
+            },
+            suggest: SuggestConfig {
+                enabled: Some(true),
+                branches: Some(BTreeMap::new()),
+                paths: Some(BTreeMap::new()),
+                rules: Some(Vec::new()),
+            },
+            ..Self::default()
+        }
+    }
+
+    /// The effective value of each setting of the layers that has one, along with where it comes
+    /// from.
+    pub fn effective(
+        layers: &[(Origin, Self)],
+    ) -> anyhow::Result<Vec<(String, toml::Value, Origin)>> {
+        let values = layers
+            .iter()
+            .map(|(origin, config)| Ok((*origin, toml::Value::try_from(config)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(keys()
+            .into_iter()
+            .filter_map(|key| {
+                let (value, origin) = values.iter().rev().find_map(|(origin, value)| {
+                    let value = key
+                        .split('.')
+                        .try_fold(value, |value, name| value.get(name))?;
+                    Some((value.clone(), *origin))
+                })?;
+
+                Some((key, value, origin))
+            })
+            .collect())
+    }
+
+    /// The user's `config.toml`, in the config directory, with the settings of the given profile.
+    ///
+    /// Like `.gimoji.toml`, it can be written in JSON or YAML instead, as `config.json` or
+    /// `config.yaml`.
+    pub fn user(profile: Option<&str>) -> anyhow::Result<Self> {
+        match paths::config_dir() {
+            Some(dir) => Self::read(&find_file(&dir.join(CONFIG_FILE)), profile),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The `.gimoji.toml` file at the root of the current repository, with the settings of the
+    /// given profile.
+    pub fn repo(profile: Option<&str>) -> anyhow::Result<Self> {
+        match git::repo_root() {
+            Some(root) => Self::read(&find_file(&root.join(REPO_CONFIG_FILE)), profile),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The `gimoji.<key>` git config, e.g. `gimoji.set` or `gimoji.search.match-mode`.
+    ///
+    /// Values are parsed as TOML where possible, e.g. `2` as a number or `["code", "name"]` as an
+    /// array, and taken as strings otherwise. The settings of the given profile are set as
+    /// `gimoji.profile.<name>.<key>`.
+    pub fn git(profile: Option<&str>) -> anyhow::Result<Self> {
+        let mut table = toml::Table::new();
+        for (key, value) in git::config_get_all(GIT_CONFIG_PREFIX) {
+            // The emoji database file of the repository is not a setting.
+            if key == REPO_DATABASE_GIT_CONFIG {
+                continue;
+            }
+            if let Some(key) = key.strip_prefix(GIT_CONFIG_PREFIX) {
+                insert(&mut table, &renamed(key), parse_value(&value))
+                    .with_context(|| format!("Invalid `{GIT_CONFIG_PREFIX}{key}` git config"))?;
+            }
+        }
+
+        let profiles = table.remove(PROFILE_KEY);
+        let mut config: Self = table.try_into().context("Invalid `gimoji.*` git config")?;
+        if let Some(profiles) = profiles {
+            let toml::Value::Table(profiles) = profiles else {
+                bail!("Invalid `{GIT_CONFIG_PREFIX}{PROFILE_KEY}` git config");
+            };
+            for (name, settings) in profiles {
+                if profile == Some(name.as_str()) {
+                    let settings: Self = settings.try_into().with_context(|| {
+                        format!("Invalid `{GIT_CONFIG_PREFIX}{PROFILE_KEY}.{name}.*` git config")
+                    })?;
+                    config = config.layered(settings);
+                }
+                config.profiles.push(name);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// The `GIMOJI_<KEY>` environment variables, e.g. `GIMOJI_SET` or `GIMOJI_SEARCH_MATCH_MODE`.
+    ///
+    /// Values are parsed the same way as the ones of the git config.
+    pub fn env() -> anyhow::Result<Self> {
+        let mut table = toml::Table::new();
+        for key in keys() {
+            // The variables of renamed settings still work, unless the new one is set.
+            let old_keys = RENAMES
+                .iter()
+                .flat_map(|renames| renames.iter())
+                .filter(|(_, new)| *new == key)
+                .map(|(old, _)| *old);
+            let set = [key.as_str()].into_iter().chain(old_keys).find_map(|key| {
+                let var = env_var(key);
+                env::var(&var).ok().map(|value| (var, value))
+            });
+            if let Some((var, value)) = set {
+                insert(&mut table, &key, parse_value(&value))
+                    .with_context(|| format!("Invalid `{var}` environment variable"))?;
+            }
+        }
+
+        table
+            .try_into()
+            .context("Invalid `GIMOJI_*` environment variables")
+    }
+
+    /// These settings, overridden by the ones set in `other`.
+    pub fn layered(self, other: Self) -> Self {
+        Self {
+            color_scheme: other.color_scheme.or(self.color_scheme),
+            detect_color_scheme: other.detect_color_scheme.or(self.detect_color_scheme),
+            default_color_scheme: other.default_color_scheme.or(self.default_color_scheme),
+            set: other.set.or(self.set),
+            language: other.language.or(self.language),
+            order: other.order.or(self.order),
+            picker: other.picker.or(self.picker),
+            preselect: other.preselect.or(self.preselect),
+            emoji_format: other.emoji_format.or(self.emoji_format),
+            output_format: other.output_format.or(self.output_format),
+            clipboard: other.clipboard.or(self.clipboard),
+            copy_format: other.copy_format.or(self.copy_format),
+            clipboard_backend: other.clipboard_backend.or(self.clipboard_backend),
+            clipboard_command: other.clipboard_command.or(self.clipboard_command),
+            clipboard_selection: other.clipboard_selection.or(self.clipboard_selection),
+            clipboard_timeout: other.clipboard_timeout.or(self.clipboard_timeout),
+            tmux_buffer: other.tmux_buffer.or(self.tmux_buffer),
+            colors: self.colors.layered(other.colors),
+            keys: self.keys.layered(other.keys),
+            hook: self.hook.layered(other.hook),
+            lint: self.lint.layered(other.lint),
+            search: self.search.layered(other.search),
+            database: self.database.layered(other.database),
+            history: self.history.layered(other.history),
+            suggest: self.suggest.layered(other.suggest),
+            profile: other.profile.or(self.profile),
+            profiles: self.profiles.into_iter().chain(other.profiles).collect(),
+            strict: self.strict || other.strict,
+        }
+    }
+
+    fn read(path: &Path, profile: Option<&str>) -> anyhow::Result<Self> {
+        let Some((text, _)) = read_document(path)? else {
+            return Ok(Self::default());
+        };
+
+        let (mut config, profiles) = Self::parse(&text, Format::of(path))
+            .with_context(|| format!("Invalid config file `{}`", path.display()))?;
+        for (name, settings) in profiles {
+            if profile == Some(name.as_str()) {
+                config = config.layered(settings);
+            }
+            config.profiles.push(name);
+        }
+        if let Some(dir) = path.parent() {
+            for file in config.database.files.iter_mut().flatten() {
+                *file = dir.join(&*file);
+            }
+            if let Some(history_dir) = &mut config.history.dir {
+                *history_dir = dir.join(&*history_dir);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// The settings of a config file in the given format, along with the ones of each of its
+    /// profiles.
+    ///
+    /// Unknown settings and invalid values are errors, located by line and column in the file.
+    fn parse(text: &str, format: Format) -> anyhow::Result<(Self, Vec<(String, Self)>)> {
+        let FileSettings(config) = format.deserialize(text)?;
+        let FileProfiles { profiles } = format.deserialize(text)?;
+
+        Ok((config, profiles.into_iter().collect()))
+    }
+}
+
+/// A place settings can be set in, see [`Settings`](crate::settings::Settings).
+#[derive(Debug, Clone, Copy)]
+pub enum Origin {
+    Default,
+    /// gitmoji-cli's `.gitmojirc.json`.
+    GitmojiRc,
+    /// The user's `config.toml`.
+    User,
+    Git,
+    /// The `.gimoji.toml` file of the current repository.
+    Repo,
+    Env,
+    Cli,
+}
+
+impl Origin {
+    /// Where exactly the setting of the given key is set, e.g. `env:GIMOJI_SET` or
+    /// `repo:/path/to/repo/.gimoji.toml`.
+    pub fn describe(self, key: &str) -> String {
+        let file = |name: &str, file: ConfigFile| match file.path() {
+            Ok(path) => format!("{name}:{}", path.display()),
+            Err(_) => name.to_owned(),
+        };
+
+        match self {
+            Self::Default => "default".to_owned(),
+            Self::GitmojiRc => "gitmojirc".to_owned(),
+            Self::User => file("user", ConfigFile::User),
+            Self::Git => format!("git:{GIT_CONFIG_PREFIX}{key}"),
+            Self::Repo => file("repo", ConfigFile::Repo),
+            Self::Env => format!("env:{}", env_var(key)),
+            Self::Cli => "cli".to_owned(),
+        }
+    }
+}
+
+/// A config file that can be edited with `gimoji config`.
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigFile {
+    /// The user's `config.toml`.
+    User,
+    /// The `.gimoji.toml` file of the current repository.
+    Repo,
+}
+
+impl ConfigFile {
+    pub fn path(self) -> anyhow::Result<PathBuf> {
+        match self {
+            Self::User => paths::config_dir()
+                .map(|dir| find_file(&dir.join(CONFIG_FILE)))
+                .context("Failed to find the config directory"),
+            Self::Repo => git::repo_root()
+                .map(|root| find_file(&root.join(REPO_CONFIG_FILE)))
+                .context("Not in a git repository"),
+        }
+    }
+
+    /// The value of the setting of the given dot-separated key, if it's set in the file, or in its
+    /// table of the given profile.
+    ///
+    /// Strings are returned as is, and other values in TOML.
+    pub fn get(self, key: &str, profile: Option<&str>) -> anyhow::Result<Option<String>> {
+        check_key(key)?;
+        let (_, document) = read_document(&self.path()?)?.unwrap_or_default();
+        let mut item = document.as_item();
+        for name in profile_key(key, profile).split('.') {
+            match item.get(name) {
+                Some(value) => item = value,
+                None => return Ok(None),
+            }
+        }
+
+        let Ok(mut value) = item.clone().into_value() else {
+            return Ok(None);
+        };
+        value.decor_mut().clear();
+
+        Ok(Some(match value.as_str() {
+            Some(value) => value.to_owned(),
+            None => value.to_string(),
+        }))
+    }
+
+    /// Set the setting of the given dot-separated key in the file, creating it if needed, or in its
+    /// table of the given profile.
+    ///
+    /// The value is parsed as TOML where possible, and taken as a string otherwise, like the ones
+    /// of the git config. The rest of TOML files, comments included, is left untouched.
+    pub fn set(self, key: &str, value: &str, profile: Option<&str>) -> anyhow::Result<()> {
+        check_key(key)?;
+        let path = self.path()?;
+        let mut document = match read_document(&path)? {
+            Some((_, document)) => document,
+            None => {
+                let mut document = toml_edit::DocumentMut::new();
+                document.insert(VERSION_KEY, toml_edit::value(CONFIG_VERSION));
+                document
+            }
+        };
+        let value: toml_edit::Value = parse_value(value)
+            .to_string()
+            .parse()
+            .expect("TOML values are valid TOML");
+        let key = profile_key(key, profile);
+        insert_item(&mut document, &key, toml_edit::Item::Value(value))
+            .with_context(|| format!("Failed to set `{key}` in `{}`", path.display()))?;
+
+        let format = Format::of(&path);
+        Config::parse(&format.serialize(&document), format)
+            .with_context(|| format!("Invalid value for `{key}`"))?;
+
+        write_document(&path, &document)
+    }
+
+    /// Migrate the file to the current config version, keeping a copy of the original next to it,
+    /// e.g. `config.toml.v1.bak`, whose path is returned. Nothing is written if there's nothing to
+    /// migrate, in which case there's no copy.
+    pub fn migrate(self) -> anyhow::Result<Option<PathBuf>> {
+        migrate_file(&self.path()?)
+    }
+}
+
+/// Migrate the config file to the current config version, see [`ConfigFile::migrate`].
+fn migrate_file(path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let Some((text, mut document, version)) = read_versioned_document(path)? else {
+        return Ok(None);
+    };
+    if !migrate_document(path, &mut document, version)? {
+        return Ok(None);
+    }
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup = path.with_file_name(format!("{file_name}.v{version}.bak"));
+    fs::write(&backup, text).with_context(|| format!("Failed to write `{}`", backup.display()))?;
+    write_document(path, &document)?;
+
+    Ok(Some(backup))
+}
+
+/// Read the config file, along with it as an editable TOML document whatever its format, if it
+/// exists.
+///
+/// Files of older config versions are migrated to the current one in memory only, with a warning
+/// to migrate them for good with `gimoji config migrate`. Files of newer versions are rejected
+/// rather than misread.
+fn read_document(path: &Path) -> anyhow::Result<Option<(String, toml_edit::DocumentMut)>> {
+    let Some((text, mut document, version)) = read_versioned_document(path)? else {
+        return Ok(None);
+    };
+    if !migrate_document(path, &mut document, version)? {
+        return Ok(Some((text, document)));
+    }
+    eprintln!(
+        "WARNING: `{}` is in the format of config version {version}, run `gimoji config migrate` \
+        to update it to version {CONFIG_VERSION}",
+        path.display()
+    );
+
+    Ok(Some((Format::of(path).serialize(&document), document)))
+}
+
+/// Read the config file, along with it as an editable TOML document whatever its format and its
+/// config version, if it exists.
+fn read_versioned_document(
+    path: &Path,
+) -> anyhow::Result<Option<(String, toml_edit::DocumentMut, i64)>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read `{}`", path.display())),
+    };
+    let document = Format::of(path)
+        .parse(&text)
+        .with_context(|| format!("Invalid config file `{}`", path.display()))?;
+
+    // Files from before versioning are version 1.
+    let version = match document.get(VERSION_KEY) {
+        None => 1,
+        Some(version) => version
+            .as_integer()
+            .filter(|version| *version >= 1)
+            .with_context(|| {
+                format!(
+                    "Invalid config file `{}`: `{VERSION_KEY}` is not a version number",
+                    path.display()
+                )
+            })?,
+    };
+    if version > CONFIG_VERSION {
+        bail!(
+            "The config file `{}` is for a newer version of gimoji (config version {version}, \
+            this one supports up to {CONFIG_VERSION}), please upgrade",
+            path.display()
+        );
+    }
+
+    Ok(Some((text, document, version)))
+}
+
+/// Rename the settings of the document of the given config version to their current keys, in its
+/// profiles too, and stamp it with the current version, unless nothing had to be renamed.
+///
+/// Returns whether anything was renamed.
+fn migrate_document(
+    path: &Path,
+    document: &mut toml_edit::DocumentMut,
+    version: i64,
+) -> anyhow::Result<bool> {
+    let profiles: Vec<String> = document
+        .get(PROFILE_KEY)
+        .and_then(toml_edit::Item::as_table_like)
+        .map(|profiles| profiles.iter().map(|(name, _)| name.to_owned()).collect())
+        .unwrap_or_default();
+    let mut migrated = false;
+    for renames in &RENAMES[version as usize - 1..] {
+        for (old, new) in *renames {
+            let keys = [None]
+                .into_iter()
+                .chain(profiles.iter().map(|profile| Some(profile.as_str())))
+                .map(|profile| (profile_key(old, profile), profile_key(new, profile)));
+            for (old, new) in keys {
+                let Some((decor, item)) = remove_item(document, &old) else {
+                    continue;
+                };
+                migrated = true;
+                let mut key = insert_item(document, &new, item).with_context(|| {
+                    format!(
+                        "Failed to migrate `{old}` to `{new}` in `{}`",
+                        path.display()
+                    )
+                })?;
+                // Keep the comments around the setting.
+                *key.leaf_decor_mut() = decor;
+            }
+        }
+    }
+    if migrated {
+        document.insert(VERSION_KEY, toml_edit::value(CONFIG_VERSION));
+    }
+
+    Ok(migrated)
+}
+
+fn write_document(path: &Path, document: &toml_edit::DocumentMut) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create `{}`", dir.display()))?;
+    }
+
+    fs::write(path, Format::of(path).serialize(document))
+        .with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+/// The config file at the given path, or the first one existing with the same name in another
+/// format, defaulting to the given one.
+fn find_file(path: &Path) -> PathBuf {
+    Format::EXTENSIONS
+        .iter()
+        .map(|(extension, _)| path.with_extension(extension))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// The format of a config file, from its extension.
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// The supported extensions, in order of preference.
+    const EXTENSIONS: [(&str, Self); 4] = [
+        ("toml", Self::Toml),
+        ("json", Self::Json),
+        ("yaml", Self::Yaml),
+        ("yml", Self::Yaml),
+    ];
+
+    /// The format of the file at the given path, TOML unless its extension is of another one.
+    fn of(path: &Path) -> Self {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        Self::EXTENSIONS
+            .iter()
+            .find(|(ext, _)| extension == Some(*ext))
+            .map_or(Self::Toml, |(_, format)| *format)
+    }
+
+    /// Parse the file as an editable TOML document.
+    fn parse(self, text: &str) -> anyhow::Result<toml_edit::DocumentMut> {
+        let table: toml::Table = match self {
+            Self::Toml => {
+                return text
+                    .parse()
+                    .map_err(|e: toml_edit::TomlError| toml_error(text, e.message(), e.span()))
+            }
+            _ => self.deserialize(text)?,
+        };
+
+        Ok(table
+            .to_string()
+            .parse()
+            .expect("TOML tables are valid TOML"))
+    }
+
+    /// Deserialize the file, with errors located by line and column.
+    fn deserialize<T: DeserializeOwned + Default>(self, text: &str) -> anyhow::Result<T> {
+        Ok(match self {
+            Self::Toml => {
+                toml::from_str(text).map_err(|e| toml_error(text, e.message(), e.span()))?
+            }
+            Self::Json => serde_json::from_str(text)?,
+            // An empty YAML file has no settings.
+            Self::Yaml => serde_yaml::from_str::<Option<_>>(text)?.unwrap_or_default(),
+        })
+    }
+
+    /// Write the TOML document in this format.
+    fn serialize(self, document: &toml_edit::DocumentMut) -> String {
+        let table = || -> toml::Table {
+            toml::from_str(&document.to_string()).expect("TOML documents are valid TOML")
+        };
+
+        match self {
+            Self::Toml => document.to_string(),
+            Self::Json => {
+                let json = serde_json::to_string_pretty(&table()).expect("TOML is valid JSON");
+                format!("{json}\n")
+            }
+            Self::Yaml => serde_yaml::to_string(&table()).expect("TOML is valid YAML"),
+        }
+    }
+}
+
+/// An error in a TOML file, located by line and column like the JSON and YAML ones.
+fn toml_error(text: &str, message: &str, span: Option<Range<usize>>) -> anyhow::Error {
+    // Parse errors come with what's expected on a line of its own.
+    let message = message.trim_end().replace('\n', ", ");
+    let Some(span) = span else {
+        return anyhow!("{message}");
+    };
+    let before = &text[..span.start];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    anyhow!("{message} at line {line} column {column}")
+}
+
+/// The settings of a config file, i.e. everything but its version and profiles.
+#[derive(Default)]
+struct FileSettings(Config);
+
+impl<'de> Deserialize<'de> for FileSettings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Config::deserialize(SettingsDeserializer(deserializer)).map(Self)
+    }
+}
+
+/// The profiles of a config file, the rest of which is ignored.
+#[derive(Deserialize, Default)]
+struct FileProfiles {
+    #[serde(default, rename = "profile")]
+    profiles: BTreeMap<String, Config>,
+}
+
+/// A deserializer leaving out the version and profiles of a config file.
+///
+/// Skipping them rather than removing them from the file beforehand keeps the positions of errors
+/// right.
+struct SettingsDeserializer<D>(D);
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for SettingsDeserializer<D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_any(SettingsVisitor(visitor))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct SettingsVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for SettingsVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.0.visit_map(SettingsMap(map))
+    }
+}
+
+/// The entries of a config file, without its version and profiles.
+struct SettingsMap<A>(A);
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for SettingsMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let mut seed = Some(seed);
+        loop {
+            match self.0.next_key_seed(SettingsKey(&mut seed))? {
+                Some(Some(key)) => return Ok(Some(key)),
+                Some(None) => {
+                    self.0.next_value::<de::IgnoredAny>()?;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.next_value_seed(seed)
+    }
+}
+
+/// The key of an entry of a config file, deserialized with the seed unless it's to be skipped, in
+/// which case the seed is kept for the next one.
+struct SettingsKey<'s, K>(&'s mut Option<K>);
+
+impl<'de, K: DeserializeSeed<'de>> DeserializeSeed<'de> for SettingsKey<'_, K> {
+    type Value = Option<K::Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        if key == VERSION_KEY || key == PROFILE_KEY {
+            return Ok(None);
+        }
+        let seed = self.0.take().expect("The seed is only used once");
+
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+}
+
+/// Insert the item at the dot-separated key, creating its tables as needed.
+fn insert_item<'d>(
+    document: &'d mut toml_edit::DocumentMut,
+    key: &str,
+    item: toml_edit::Item,
+) -> anyhow::Result<toml_edit::KeyMut<'d>> {
+    let mut path: Vec<_> = key.split('.').collect();
+    let name = path.pop().unwrap_or_default();
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for section in path {
+        if table.get(section).is_none() {
+            // Only the tables with settings of their own show up in the file.
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            table.insert(section, toml_edit::Item::Table(new_table));
+        }
+        table = table
+            .get_mut(section)
+            .and_then(toml_edit::Item::as_table_like_mut)
+            .with_context(|| format!("`{section}` is not a table"))?;
+    }
+    table.insert(name, item);
+
+    Ok(table.key_mut(name).expect("The item was just inserted"))
+}
+
+/// Remove the item at the dot-separated key, if any, along with the decor of its key.
+fn remove_item(
+    document: &mut toml_edit::DocumentMut,
+    key: &str,
+) -> Option<(toml_edit::Decor, toml_edit::Item)> {
+    let mut path: Vec<_> = key.split('.').collect();
+    let name = path.pop().unwrap_or_default();
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for section in path {
+        table = table.get_mut(section)?.as_table_like_mut()?;
+    }
+    let decor = table.key(name)?.leaf_decor().clone();
+
+    table.remove(name).map(|item| (decor, item))
+}
+
+/// The current key of a setting, which may have been renamed since, in a profile or not.
+fn renamed(key: &str) -> String {
+    let profile_setting = key
+        .strip_prefix(PROFILE_KEY)
+        .and_then(|key| key.strip_prefix('.')?.split_once('.'));
+    if let Some((profile, key)) = profile_setting {
+        return profile_key(&renamed(key), Some(profile));
+    }
+
+    RENAMES
+        .iter()
+        .flat_map(|renames| renames.iter())
+        .fold(key, |key, (old, new)| if key == *old { new } else { key })
+        .to_owned()
+}
+
+/// The key of the setting in the given profile's table, if any.
+fn profile_key(key: &str, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{PROFILE_KEY}.{profile}.{key}"),
+        None => key.to_owned(),
+    }
+}
+
+/// Fail if the dot-separated key isn't the one of a setting.
+fn check_key(key: &str) -> anyhow::Result<()> {
+    if !keys().iter().any(|k| k == key) {
+        let new = renamed(key);
+        if new != key {
+            bail!("Unknown setting `{key}`, it was renamed to `{new}`");
+        }
+        bail!("Unknown setting `{key}`");
+    }
+
+    Ok(())
+}
+
+/// The `[colors]` table, with the colors of the light and dark color schemes.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub light: ThemeConfig,
+    pub dark: ThemeConfig,
+}
+
+impl ColorsConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            light: self.light.layered(other.light),
+            dark: self.dark.layered(other.dark),
+        }
+    }
+}
+
+/// The colors of a color scheme, overriding its default ones.
+///
+/// Colors are given by name (e.g. `green` or `light-blue`), as an RGB value (e.g. `#00ff00`) or as
+/// an index in the terminal's palette (e.g. `"10"`).
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub selected: Option<Color>,
+    pub unselected: Option<Color>,
+    pub border: Option<Color>,
+}
+
+impl From<Colors> for ThemeConfig {
+    fn from(colors: Colors) -> Self {
+        Self {
+            selected: Some(colors.selected),
+            unselected: Some(colors.unselected),
+            border: Some(colors.border),
+        }
+    }
+}
+
+impl ThemeConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            selected: other.selected.or(self.selected),
+            unselected: other.unselected.or(self.unselected),
+            border: other.border.or(self.border),
+        }
+    }
+
+    /// The given colors, with the ones set here instead.
+    pub fn apply(&self, colors: Colors) -> Colors {
+        Colors {
+            selected: self.selected.unwrap_or(colors.selected),
+            unselected: self.unselected.unwrap_or(colors.unselected),
+            border: self.border.unwrap_or(colors.border),
+        }
+    }
+}
+
+/// The `[keys]` table, with the keys bound to each action of the picker, replacing its default
+/// ones.
+///
+/// Keys are given as a chord like `ctrl+s`, or a list of them.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct KeysConfig {
+    pub select: Option<Chords>,
+    pub cancel: Option<Chords>,
+    pub exit: Option<Chords>,
+    pub up: Option<Chords>,
+    pub down: Option<Chords>,
+    pub delete_char: Option<Chords>,
+    pub cycle_set: Option<Chords>,
+    pub cycle_match_mode: Option<Chords>,
+}
+
+impl From<Keymap> for KeysConfig {
+    fn from(keymap: Keymap) -> Self {
+        let chords = |action| keymap.chords(action).cloned();
+
+        Self {
+            select: chords(Action::Select),
+            cancel: chords(Action::Cancel),
+            exit: chords(Action::Exit),
+            up: chords(Action::Up),
+            down: chords(Action::Down),
+            delete_char: chords(Action::DeleteChar),
+            cycle_set: chords(Action::CycleSet),
+            cycle_match_mode: chords(Action::CycleMatchMode),
+        }
+    }
+}
+
+impl KeysConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            select: other.select.or(self.select),
+            cancel: other.cancel.or(self.cancel),
+            exit: other.exit.or(self.exit),
+            up: other.up.or(self.up),
+            down: other.down.or(self.down),
+            delete_char: other.delete_char.or(self.delete_char),
+            cycle_set: other.cycle_set.or(self.cycle_set),
+            cycle_match_mode: other.cycle_match_mode.or(self.cycle_match_mode),
+        }
+    }
+
+    /// The default keymap, with the keys set here instead.
+    pub fn keymap(self) -> Keymap {
+        Keymap::new([
+            (Action::Select, self.select),
+            (Action::Cancel, self.cancel),
+            (Action::Exit, self.exit),
+            (Action::Up, self.up),
+            (Action::Down, self.down),
+            (Action::DeleteChar, self.delete_char),
+            (Action::CycleSet, self.cycle_set),
+            (Action::CycleMatchMode, self.cycle_match_mode),
+        ])
+    }
+}
+
+/// The `[hook]` table.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HookConfig {
+    /// Replace the codes retired by gitmoji with their current equivalents.
+    pub migrate_codes: Option<bool>,
+    /// Where the emoji goes in the first line of the message.
+    pub position: Option<Position>,
+    /// How the emoji is written in commit messages, instead of the general `emoji-format`.
+    pub emoji_format: Option<EmojiFormat>,
+    /// The sources of the commit messages to add an emoji to, when they don't come from scratch.
+    pub sources: Option<Vec<MessageSource>>,
+    /// What to do with messages that already have an emoji.
+    pub existing: Option<Existing>,
+    /// Leave commit messages alone when running in CI, i.e. with the `CI` environment variable set.
+    pub skip_in_ci: Option<bool>,
+    /// Don't prompt for an emoji when there's no terminal to do so, instead of failing.
+    pub skip_without_tty: Option<bool>,
+    /// How much of the commit message the picker shows above the search.
+    pub show_message: Option<ShowMessage>,
+}
+
+impl HookConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            migrate_codes: other.migrate_codes.or(self.migrate_codes),
+            position: other.position.or(self.position),
+            emoji_format: other.emoji_format.or(self.emoji_format),
+            sources: other.sources.or(self.sources),
+            existing: other.existing.or(self.existing),
+            skip_in_ci: other.skip_in_ci.or(self.skip_in_ci),
+            skip_without_tty: other.skip_without_tty.or(self.skip_without_tty),
+            show_message: other.show_message.or(self.show_message),
+        }
+    }
+}
+
+/// The `[lint]` table, with the rules `gimoji lint` checks commit messages against.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LintConfig {
+    /// Whether the subject must have an emoji.
+    pub require_emoji: Option<bool>,
+    /// Where the emoji must be in the subject.
+    pub position: Option<Position>,
+    /// Codes of the only emojis allowed.
+    pub allowed: Option<Vec<String>>,
+    /// The maximum number of emojis in the subject.
+    pub max_emojis: Option<usize>,
+    /// Whether the emoji must be the one of the conventional commit type the subject starts with,
+    /// with sets keyed to commit types.
+    pub match_commit_type: Option<bool>,
+}
+
+impl LintConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            require_emoji: other.require_emoji.or(self.require_emoji),
+            position: other.position.or(self.position),
+            allowed: other.allowed.or(self.allowed),
+            max_emojis: other.max_emojis.or(self.max_emojis),
+            match_commit_type: other.match_commit_type.or(self.match_commit_type),
+        }
+    }
+}
+
+/// The `[search]` table.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SearchConfig {
+    pub match_mode: Option<MatchMode>,
+    pub case_sensitive: Option<bool>,
+    pub typo_tolerance: Option<usize>,
+    /// The fields searched by terms without a field prefix.
+    pub fields: Option<Vec<Field>>,
+    /// Groups of interchangeable words, added to the built-in ones.
+    pub synonyms: Option<Vec<Vec<String>>>,
+}
+
+impl SearchConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            match_mode: other.match_mode.or(self.match_mode),
+            case_sensitive: other.case_sensitive.or(self.case_sensitive),
+            typo_tolerance: other.typo_tolerance.or(self.typo_tolerance),
+            fields: other.fields.or(self.fields),
+            synonyms: other.synonyms.or(self.synonyms),
+        }
+    }
+}
+
+/// The `[database]` table, with the emoji sets to choose from and the emojis to merge into them.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// The sets available in the picker, in the order they're cycled through.
+    pub sets: Option<Vec<String>>,
+    /// Emoji database files, relative to the config file setting them.
+    pub files: Option<Vec<PathBuf>>,
+    /// Codes of the emojis to leave out.
+    pub hide: Option<Vec<String>>,
+    /// Emojis defined inline, following the same schema as the ones of database files.
+    pub gitmojis: Option<Vec<toml::Table>>,
+}
+
+impl DatabaseConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            sets: other.sets.or(self.sets),
+            files: other.files.or(self.files),
+            hide: other.hide.or(self.hide),
+            gitmojis: other.gitmojis.or(self.gitmojis),
+        }
+    }
+}
+
+/// The `[history]` table, about the record of the emojis selected in the picker.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: Option<bool>,
+    /// The directory the history is stored in, relative to the config file setting it.
+    pub dir: Option<PathBuf>,
+    /// The maximum number of selections kept.
+    pub max_entries: Option<usize>,
+    /// How many days selections are kept for.
+    pub max_age_days: Option<u64>,
+    /// Whether the picker starts with the last search, selected so that typing replaces it.
+    pub remember_query: Option<bool>,
+}
+
+impl HistoryConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            enabled: other.enabled.or(self.enabled),
+            dir: other.dir.or(self.dir),
+            max_entries: other.max_entries.or(self.max_entries),
+            max_age_days: other.max_age_days.or(self.max_age_days),
+            remember_query: other.remember_query.or(self.remember_query),
+        }
+    }
+}
+
+/// The `[suggest]` table, about the emojis suggested by `gimoji suggest` and put first in the
+/// hook's picker from what the commit is made on: the branch, the staged paths and the staged diff.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SuggestConfig {
+    /// Whether the hook's picker starts with the suggested emojis.
+    pub enabled: Option<bool>,
+    /// The emojis, as codes or emojis, suggested by the words of branch names, added to the
+    /// built-in ones. An empty emoji removes the built-in one of the word.
+    pub branches: Option<BTreeMap<String, String>>,
+    /// The emojis, as codes or emojis, suggested by the glob patterns matching the staged paths,
+    /// added to the built-in ones. An empty emoji removes the built-in one of the pattern.
+    pub paths: Option<BTreeMap<String, String>>,
+    /// Rules suggesting emojis for the commits matching their patterns of the branch, the staged
+    /// paths and the staged diff.
+    pub rules: Option<Vec<Rule>>,
+}
+
+impl SuggestConfig {
+    fn layered(self, other: Self) -> Self {
+        Self {
+            enabled: other.enabled.or(self.enabled),
+            branches: other.branches.or(self.branches),
+            paths: other.paths.or(self.paths),
+            rules: other.rules.or(self.rules),
+        }
+    }
+}
+
+/// Insert the value at the dot-separated key, e.g. `search.fields`, creating its tables as needed.
+fn insert(table: &mut toml::Table, key: &str, value: toml::Value) -> anyhow::Result<()> {
+    let mut path: Vec<_> = key.split('.').collect();
+    let name = path.pop().unwrap_or_default();
+    let mut table = table;
+    for section in path {
+        let entry = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let Some(section) = entry.as_table_mut() else {
+            bail!("`{section}` is not a table");
+        };
+        table = section;
+    }
+    table.insert(name.to_owned(), value);
+
+    Ok(())
+}
+
+/// The environment variable of the setting of the given key, e.g. `GIMOJI_SEARCH_MATCH_MODE`.
+fn env_var(key: &str) -> String {
+    format!(
+        "{ENV_PREFIX}{}",
+        key.to_uppercase().replace(['.', '-'], "_")
+    )
+}
+
+/// The dot-separated keys of all the settings, e.g. `search.match-mode`.
+///
+/// These are found by deserializing [`Config`] with a [`KeyCollector`], so that there's no list to
+/// keep in sync with it.
+fn keys() -> Vec<String> {
+    let keys = RefCell::new(Vec::new());
+    Config::deserialize(KeyCollector {
+        key: String::new(),
+        keys: &keys,
+    })
+    .expect("Settings are all optional");
+
+    keys.into_inner()
+}
+
+/// A deserializer that records the keys of the optional values deserialized from it, without any
+/// actual value.
+struct KeyCollector<'k> {
+    /// The key of the value being deserialized.
+    key: String,
+    keys: &'k RefCell<Vec<String>>,
+}
+
+impl<'de> Deserializer<'de> for KeyCollector<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.keys.borrow_mut().push(self.key);
+        visitor.visit_none()
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(FieldCollector {
+            key: self.key,
+            fields: fields.iter(),
+            field: "",
+            keys: self.keys,
+        })
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom(format!(
+            "`{}` is neither optional nor a table",
+            self.key
+        )))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// The fields of a struct deserialized from a [`KeyCollector`].
+struct FieldCollector<'k> {
+    /// The key of the struct.
+    key: String,
+    fields: slice::Iter<'static, &'static str>,
+    /// The field whose value is to be deserialized next.
+    field: &'static str,
+    keys: &'k RefCell<Vec<String>>,
+}
+
+impl<'de> MapAccess<'de> for FieldCollector<'_> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(field) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.field = field;
+
+        seed.deserialize(field.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let key = if self.key.is_empty() {
+            self.field.to_owned()
+        } else {
+            format!("{}.{}", self.key, self.field)
+        };
+
+        seed.deserialize(KeyCollector {
+            key,
+            keys: self.keys,
+        })
+    }
+}
+
+/// A git config value, as TOML if it's valid as such, or as a string otherwise.
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+/// The version of the config files, bumped whenever settings are renamed.
+const CONFIG_VERSION: i64 = 2;
+const VERSION_KEY: &str = "version";
+const PROFILE_KEY: &str = "profile";
+const PROFILE_ENV: &str = "GIMOJI_PROFILE";
+/// The settings renamed by each version of the config files after the first one, from their old key
+/// to their new one.
+const RENAMES: [&[(&str, &str)]; CONFIG_VERSION as usize - 1] = [
+    // 2: The lint rule is named after what the emoji must match.
+    &[("lint.match-type", "lint.match-commit-type")],
+];
+pub const DEFAULT_TYPO_TOLERANCE: usize = 1;
+pub const DEFAULT_HISTORY_MAX_ENTRIES: usize = 1000;
+pub const DEFAULT_HOOK_SOURCES: [MessageSource; 2] = [MessageSource::Message, MessageSource::Merge];
+const CONFIG_FILE: &str = "config.toml";
+const REPO_CONFIG_FILE: &str = ".gimoji.toml";
+const GIT_CONFIG_PREFIX: &str = "gimoji.";
+const ENV_PREFIX: &str = "GIMOJI_";
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// A config file with the given name and text in a directory of its own.
+    fn write_file(test: &str, name: &str, text: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("gimoji-config-{test}-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn parse_formats() {
+        let files = [
+            (
+                Format::Toml,
+                "version = 1\nset = \"devmoji\"\n[search]\ntypo-tolerance = 2\n\
+                [profile.work]\nset = \"conventional\"\n",
+            ),
+            (
+                Format::Json,
+                r#"{ "version": 1, "set": "devmoji", "search": { "typo-tolerance": 2 },
+                "profile": { "work": { "set": "conventional" } } }"#,
+            ),
+            (
+                Format::Yaml,
+                "version: 1\nset: devmoji\nsearch:\n  typo-tolerance: 2\n\
+                profile:\n  work:\n    set: conventional\n",
+            ),
+        ];
+        for (format, text) in files {
+            let (config, profiles) = Config::parse(text, format).unwrap();
+            assert_eq!(config.set.as_deref(), Some("devmoji"), "{format:?}");
+            assert_eq!(config.search.typo_tolerance, Some(2), "{format:?}");
+            assert_eq!(profiles.len(), 1, "{format:?}");
+            assert_eq!(profiles[0].0, "work", "{format:?}");
+            assert_eq!(
+                profiles[0].1.set.as_deref(),
+                Some("conventional"),
+                "{format:?}"
+            );
+        }
+        // Empty files have no settings.
+        for format in [Format::Toml, Format::Yaml] {
+            let (config, profiles) = Config::parse("", format).unwrap();
+            assert!(config.set.is_none() && profiles.is_empty(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn search_table() {
+        let text = "[search]\nmatch-mode = \"fuzzy\"\ncase-sensitive = true\ntypo-tolerance = 0\n\
+            fields = [\"code\", \"desc\"]\nsynonyms = [[\"ship\", \"rocket\", \"launch\"]]\n";
+        let (config, _) = Config::parse(text, Format::Toml).unwrap();
+        let search = config.search;
+        assert_eq!(search.match_mode, Some(MatchMode::Fuzzy));
+        assert_eq!(search.case_sensitive, Some(true));
+        assert_eq!(search.typo_tolerance, Some(0));
+        assert_eq!(search.fields, Some(vec![Field::Code, Field::Description]));
+        assert_eq!(
+            search.synonyms,
+            Some(vec![vec![
+                "ship".to_owned(),
+                "rocket".to_owned(),
+                "launch".to_owned()
+            ]])
+        );
+
+        let error = Config::parse("[search]\nfields = [\"title\"]\n", Format::Toml)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 2"), "{error}");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = Config::parse("set = \"devmoji\"\nsett = \"gitmoji\"\n", Format::Toml)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("sett"), "{error}");
+        assert!(error.contains("line 2 column 1"), "{error}");
+
+        let error = Config::parse("[search]\nmatch-mode = \"exact\"\n", Format::Toml)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 2"), "{error}");
+    }
+
+    #[test]
+    fn layered() {
+        let lower = Config {
+            set: Some("devmoji".to_owned()),
+            order: Some(Order::Code),
+            search: SearchConfig {
+                match_mode: Some(MatchMode::Fuzzy),
+                typo_tolerance: Some(2),
+                ..SearchConfig::default()
+            },
+            profiles: vec!["work".to_owned()],
+            ..Config::default()
+        };
+        let higher = Config {
+            order: Some(Order::Usage),
+            search: SearchConfig {
+                typo_tolerance: Some(0),
+                ..SearchConfig::default()
+            },
+            profiles: vec!["home".to_owned()],
+            ..Config::default()
+        };
+
+        let config = lower.layered(higher);
+        assert_eq!(config.set.as_deref(), Some("devmoji"));
+        assert_eq!(config.order, Some(Order::Usage));
+        // Tables are layered setting by setting.
+        assert_eq!(config.search.match_mode, Some(MatchMode::Fuzzy));
+        assert_eq!(config.search.typo_tolerance, Some(0));
+        assert_eq!(config.profiles, ["work", "home"]);
+    }
+
+    #[test]
+    fn effective_settings() {
+        let layers = [
+            (Origin::Default, Config::defaults()),
+            (
+                Origin::User,
+                Config {
+                    order: Some(Order::Code),
+                    ..Config::default()
+                },
+            ),
+            (
+                Origin::Cli,
+                Config {
+                    set: Some("devmoji".to_owned()),
+                    ..Config::default()
+                },
+            ),
+        ];
+
+        let effective = Config::effective(&layers).unwrap();
+        let find = |key: &str| {
+            effective
+                .iter()
+                .find(|(k, _, _)| k == key)
+                .map(|(_, value, origin)| (value.as_str().unwrap().to_owned(), *origin))
+        };
+        assert!(matches!(find("set"), Some((set, Origin::Cli)) if set == "devmoji"));
+        assert!(matches!(find("order"), Some((order, Origin::User)) if order == "code"));
+        assert!(matches!(find("picker"), Some((_, Origin::Default))));
+        assert!(find("color-scheme").is_none());
+    }
+
+    #[test]
+    fn read_profiles() {
+        let path = write_file(
+            "profiles",
+            "config.toml",
+            "set = \"devmoji\"\n[history]\ndir = \"history\"\n\
+            [profile.work]\nset = \"conventional\"\n[profile.home]\norder = \"code\"\n",
+        );
+
+        let config = Config::read(&path, None).unwrap();
+        assert_eq!(config.set.as_deref(), Some("devmoji"));
+        assert_eq!(config.order, None);
+        assert_eq!(config.profiles, ["home", "work"]);
+        // Paths are relative to the file.
+        assert_eq!(
+            config.history.dir.as_deref(),
+            Some(&*path.with_file_name("history"))
+        );
+
+        let config = Config::read(&path, Some("work")).unwrap();
+        assert_eq!(config.set.as_deref(), Some("conventional"));
+        assert_eq!(config.order, None);
+
+        // Missing files have no settings.
+        let config = Config::read(&path.with_file_name("missing.toml"), None).unwrap();
+        assert!(config.set.is_none());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn find_files_in_other_formats() {
+        let path = write_file("formats", "config.json", "{}");
+        assert_eq!(find_file(&path.with_extension("toml")), path);
+        assert_eq!(
+            find_file(&path.with_file_name("other.toml")),
+            path.with_file_name("other.toml")
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn versions() {
+        let path = write_file(
+            "versions",
+            "config.toml",
+            "version = 2\nset = \"devmoji\"\n",
+        );
+        let (_, mut document, version) = read_versioned_document(&path).unwrap().unwrap();
+        assert_eq!(version, 2);
+        assert!(!migrate_document(&path, &mut document, version).unwrap());
+        assert_eq!(migrate_file(&path).unwrap(), None);
+
+        // Files from before versioning are version 1.
+        let path = write_file("versions", "old.toml", "set = \"devmoji\"\n");
+        let (_, _, version) = read_versioned_document(&path).unwrap().unwrap();
+        assert_eq!(version, 1);
+
+        let path = write_file("versions", "newer.toml", "version = 99\n");
+        let error = read_versioned_document(&path).unwrap_err().to_string();
+        assert!(error.contains("newer version of gimoji"), "{error}");
+
+        let path = write_file("versions", "invalid.toml", "version = \"one\"\n");
+        assert!(read_versioned_document(&path).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn migrate_renamed_settings() {
+        let old = "set = \"devmoji\"\n\n[lint]\n# Emojis of commit types.\nmatch-type = true\n\n\
+            [profile.work.lint]\nmatch-type = false\n";
+        let path = write_file("migrate", "config.toml", old);
+
+        // Read as if migrated, but left alone.
+        let config = Config::read(&path, Some("work")).unwrap();
+        assert_eq!(config.lint.match_commit_type, Some(false));
+        assert_eq!(fs::read_to_string(&path).unwrap(), old);
+
+        let backup = migrate_file(&path).unwrap().unwrap();
+        assert_eq!(backup, path.with_file_name("config.toml.v1.bak"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), old);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "set = \"devmoji\"\nversion = 2\n\n[lint]\n# Emojis of commit types.\n\
+            match-commit-type = true\n\n[profile.work.lint]\nmatch-commit-type = false\n"
+        );
+        // Only once.
+        assert_eq!(migrate_file(&path).unwrap(), None);
+
+        // In other formats too.
+        let path = write_file(
+            "migrate",
+            "config.json",
+            r#"{ "lint": { "match-type": true } }"#,
+        );
+        migrate_file(&path).unwrap().unwrap();
+        let config = Config::read(&path, None).unwrap();
+        assert_eq!(config.lint.match_commit_type, Some(true));
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"match-commit-type\": true"));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn renamed_keys() {
+        assert_eq!(renamed("lint.match-type"), "lint.match-commit-type");
+        assert_eq!(
+            renamed("profile.work.lint.match-type"),
+            "profile.work.lint.match-commit-type"
+        );
+        assert_eq!(renamed("lint.max-emojis"), "lint.max-emojis");
+        let error = check_key("lint.match-type").unwrap_err().to_string();
+        assert!(
+            error.contains("renamed to `lint.match-commit-type`"),
+            "{error}"
+        );
+    }
+}
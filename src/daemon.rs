@@ -0,0 +1,204 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::PermissionsExt,
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::PathBuf,
+    process,
+};
+
+use anyhow::{bail, Context};
+use nix::{
+    sys::wait::{waitpid, WaitPidFlag, WaitStatus},
+    unistd::{self, ForkResult},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{hook::Compat, paths, Error};
+
+/// What the hook asks the daemon for: adding an emoji to the commit message, with the picker shown
+/// on the hook's terminal.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// The terminal the picker is shown on.
+    pub tty: PathBuf,
+    /// The directory the hook runs in, which the daemon runs the request in too.
+    pub dir: PathBuf,
+    pub msg_file: PathBuf,
+    pub compat: Option<Compat>,
+    pub migrate_codes: bool,
+}
+
+/// How the request went.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Response {
+    /// The error the request failed with, if any.
+    error: Option<String>,
+    /// Whether the user cancelled the selection.
+    cancelled: bool,
+}
+
+/// The socket the daemon listens on, in the user's runtime directory, e.g. `$XDG_RUNTIME_DIR`, or
+/// else the cache directory.
+pub fn socket_path() -> Option<PathBuf> {
+    paths::runtime_dir()
+        .or_else(paths::cache_dir)
+        .map(|dir| dir.join(SOCKET_NAME))
+}
+
+/// Serve the requests of the hook with the handler, until killed.
+///
+/// Each request is handled in a process of its own, forked from the daemon, whose standard streams
+/// are the requesting terminal. That's how whatever the handler has loaded beforehand, e.g. the
+/// database, is already there for it.
+pub fn serve(mut handle: impl FnMut(&Request) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let path = socket_path().context("Failed to find where to put the daemon's socket")?;
+    if UnixStream::connect(&path).is_ok() {
+        bail!("A daemon is already listening on `{}`", path.display());
+    }
+    // Left behind by a daemon that was killed.
+    let _ = fs::remove_file(&path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create `{}`", dir.display()))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on `{}`", path.display()))?;
+    // Requests write to commit messages, so they're only for the user.
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to restrict `{}` to the user", path.display()))?;
+    eprintln!("Listening on `{}`", path.display());
+
+    for stream in listener.incoming() {
+        reap_children();
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("WARNING: Failed to accept a request: {e}");
+                continue;
+            }
+        };
+        let mut line = String::new();
+        let request = BufReader::new(&stream)
+            .read_line(&mut line)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(serde_json::from_str::<Request>(&line)?));
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("WARNING: Failed to read a request: {e:#}");
+                continue;
+            }
+        };
+
+        // SAFETY: The daemon has no other threads, and the child ends with the request.
+        match unsafe { unistd::fork() }.context("Failed to fork the daemon")? {
+            ForkResult::Parent { .. } => {}
+            ForkResult::Child => {
+                drop(listener);
+                let result = attach(&request).and_then(|()| handle(&request));
+                let response = match result {
+                    Ok(()) => Response::default(),
+                    Err(e) => Response {
+                        cancelled: matches!(e.downcast_ref(), Some(Error::Cancelled)),
+                        error: Some(format!("{e:#}")),
+                    },
+                };
+                // The hook may be gone, which there's no one left to tell about.
+                let _ = respond(&stream, &response);
+                process::exit(0);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Have the daemon handle the request, returning `None` if no daemon is listening.
+pub fn request(request: &Request) -> anyhow::Result<Option<()>> {
+    let Some(path) = socket_path() else {
+        return Ok(None);
+    };
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None)
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to connect to `{}`", path.display()))
+        }
+    };
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .context("Failed to send the request to the daemon")?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .context("Failed to read the daemon's response")?;
+    if line.is_empty() {
+        bail!("The daemon didn't respond");
+    }
+    let response: Response =
+        serde_json::from_str(&line).context("Failed to read the daemon's response")?;
+    if response.cancelled {
+        return Err(Error::Cancelled.into());
+    }
+    match response.error {
+        Some(error) => bail!("{error}"),
+        None => Ok(Some(())),
+    }
+}
+
+/// The terminal of the current process, if it has one, on any of its standard streams.
+pub fn tty() -> Option<PathBuf> {
+    // Git runs hooks without stdin, and with stdout redirected to stderr.
+    unistd::ttyname(io::stdin())
+        .or_else(|_| unistd::ttyname(io::stdout()))
+        .or_else(|_| unistd::ttyname(io::stderr()))
+        .ok()
+}
+
+/// Make the requesting terminal the standard streams of the process, in the requesting directory.
+fn attach(request: &Request) -> anyhow::Result<()> {
+    // In a session of its own, so that the terminal the daemon was started from, if any, isn't
+    // taken for the requesting one.
+    unistd::setsid().context("Failed to detach from the daemon's terminal")?;
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&request.tty)
+        .with_context(|| format!("Failed to open `{}`", request.tty.display()))?;
+    for fd in 0..=2 {
+        unistd::dup2(tty.as_raw_fd(), fd)
+            .with_context(|| format!("Failed to attach to `{}`", request.tty.display()))?;
+    }
+    env::set_current_dir(&request.dir)
+        .with_context(|| format!("Failed to change to `{}`", request.dir.display()))
+}
+
+fn respond(mut stream: &UnixStream, response: &Response) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// Wait for the requests that are done, so they don't linger as zombies.
+fn reap_children() {
+    while let Ok(status) = waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+        if status == WaitStatus::StillAlive {
+            break;
+        }
+    }
+}
+
+const SOCKET_NAME: &str = "daemon.sock";
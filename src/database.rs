@@ -0,0 +1,485 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use crate::{
+    cache,
+    emoji::{Compressed, Emoji, Order, Semver, CONVENTIONAL, DEVMOJI, EMOJIS, GITMOJI},
+    git, locale, paths,
+    query::MAX_EMOJIS,
+    schema,
+    settings::DatabaseSettings,
+};
+
+/// All the emoji sets available for selection, one of which is selected.
+///
+/// These are the embedded emoji sets and the user's custom ones, each merged with or replaced by
+/// the user's own emojis and then the ones of the current repository, if any.
+pub struct Database {
+    sets: Box<[EmojiSet]>,
+    selected: usize,
+}
+
+impl Database {
+    /// Load the database, with the set of the given name selected.
+    ///
+    /// Custom sets are read from the `sets/<name>.json` files in the config directory, and named
+    /// `custom-<name>`. All the sets are merged with the user's emojis from `emojis.json` in the
+    /// config directory and the repository's emojis from the file set by the `gimoji.emojis` git
+    /// config (relative to the repository root), defaulting to `.gimoji/emojis.json`, then with the
+    /// files listed in the `[database]` config, in order, and finally with its inline emojis and
+    /// hidden codes.
+    ///
+    /// The emojis of each set are then sorted by decreasing weight, and only the sets listed in the
+    /// config are kept, if any. All this only happens for the selected set and the ones whose
+    /// emojis are needed, so that the embedded sets that aren't stay compressed.
+    pub fn load(set: &str, config: &DatabaseSettings) -> anyhow::Result<Self> {
+        let mut sets: Vec<_> = BUILTIN_SETS
+            .iter()
+            .map(|&(name, emojis)| {
+                EmojiSet::new(name.to_owned(), move || {
+                    // Prefer the latest gitmoji database fetched by `gimoji update`, if any.
+                    let cached = (name == GITMOJI_SET)
+                        .then(cache::gitmojis)
+                        .flatten()
+                        .and_then(|json| DatabaseFile::parse(&json).ok());
+                    match cached {
+                        Some(file) => file.leak().gitmojis,
+                        None => emojis.emojis().iter().collect(),
+                    }
+                })
+            })
+            .collect();
+
+        let mut files = Vec::new();
+        if let Some(dir) = paths::config_dir() {
+            sets.extend(custom_sets(&dir.join(CUSTOM_SETS_DIR))?);
+            files.extend(DatabaseFile::read(&dir.join(USER_DATABASE_FILE))?);
+        }
+        if let Some(root) = git::repo_root() {
+            let path = git::config_get(REPO_DATABASE_GIT_CONFIG)
+                .unwrap_or_else(|| REPO_DATABASE_FILE.to_owned());
+            files.extend(DatabaseFile::read(&root.join(path))?);
+        }
+        for path in &config.files {
+            let Some(file) = DatabaseFile::read(path)? else {
+                bail!("Emoji database `{}` not found", path.display());
+            };
+            files.push(file);
+        }
+        if !config.gitmojis.is_empty() || !config.hide.is_empty() {
+            let value = serde_json::json!({
+                "gitmojis": config.gitmojis,
+                "hide": config.hide,
+            });
+            files.push(DatabaseFile::from_value(value).context("Invalid `[database]` config")?);
+        }
+
+        let files: Arc<[_]> = files.into_iter().map(DatabaseFile::leak).collect();
+        let language = config.language.clone();
+        let sets: Vec<_> = sets
+            .into_iter()
+            .map(|emoji_set| {
+                let files = Arc::clone(&files);
+                let language = language.clone();
+                let selected = emoji_set.name == set;
+                emoji_set.map(move |name, emojis| {
+                    let mut emojis = emojis.to_vec();
+                    if let Some(language) = &language {
+                        translate(name, &mut emojis, language);
+                    }
+                    for file in files.iter() {
+                        emojis = merge(&emojis, file);
+                    }
+                    emojis.sort_by_key(|emoji| Reverse(emoji.weight));
+                    // The selected set fails to load instead.
+                    if emojis.len() > MAX_EMOJIS && !selected {
+                        eprintln!(
+                            "WARNING: Emoji set `{name}` has more than the {MAX_EMOJIS} emojis a \
+                             set can have, only the first ones are kept"
+                        );
+                        emojis.truncate(MAX_EMOJIS);
+                    }
+
+                    emojis
+                })
+            })
+            .collect();
+
+        let sets: Vec<_> = match &config.sets {
+            Some(names) => names
+                .iter()
+                .map(|name| Ok(sets[position(&sets, name)?].clone()))
+                .collect::<anyhow::Result<_>>()?,
+            None => sets,
+        };
+        let selected = position(&sets, set)?;
+        let database = Self {
+            sets: sets.into(),
+            selected,
+        };
+        if database.emojis().len() > MAX_EMOJIS {
+            bail!("Emoji set `{set}` has more than the {MAX_EMOJIS} emojis a set can have");
+        }
+
+        Ok(database)
+    }
+
+    /// The emojis of the selected set.
+    pub fn emojis(&self) -> &[&'static Emoji] {
+        self.selected_set().emojis()
+    }
+
+    pub fn selected_set(&self) -> &EmojiSet {
+        &self.sets[self.selected]
+    }
+
+    pub fn sets(&self) -> &[EmojiSet] {
+        &self.sets
+    }
+
+    /// The index of the selected set.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+}
+
+/// A named set of emojis, which are only worked out once needed.
+#[derive(Clone)]
+pub struct EmojiSet {
+    pub name: String,
+    emojis: Arc<LazyLock<Box<[&'static Emoji]>, LazyEmojis>>,
+}
+
+/// How the emojis of a set are worked out.
+type LazyEmojis = Box<dyn FnOnce() -> Box<[&'static Emoji]> + Send>;
+
+impl EmojiSet {
+    /// The set of the given name with the emojis the function returns, called at first use.
+    pub fn new(
+        name: String,
+        emojis: impl FnOnce() -> Vec<&'static Emoji> + Send + 'static,
+    ) -> Self {
+        Self {
+            name,
+            emojis: Arc::new(LazyLock::new(Box::new(|| emojis().into()))),
+        }
+    }
+
+    pub fn emojis(&self) -> &[&'static Emoji] {
+        &self.emojis
+    }
+
+    /// A copy of the set, with the emojis the function makes out of its name and emojis, also
+    /// called at first use.
+    pub fn map(
+        &self,
+        f: impl FnOnce(&str, &[&'static Emoji]) -> Vec<&'static Emoji> + Send + 'static,
+    ) -> Self {
+        let set = self.clone();
+
+        Self::new(self.name.clone(), move || f(&set.name, set.emojis()))
+    }
+
+    /// Whether the codes of this set are conventional commit types.
+    pub fn has_commit_types(&self) -> bool {
+        COMMIT_TYPE_SETS.contains(&self.name.as_str())
+    }
+
+    /// A copy of the set, with the emojis in the given order, given how many times each was
+    /// selected by code.
+    pub fn sorted(&self, order: Order, uses: &HashMap<String, usize>) -> Self {
+        let uses = uses.clone();
+
+        self.map(move |_, emojis| order.sorted(emojis, &uses).into_vec())
+    }
+}
+
+/// Translate the descriptions of the emojis of the set of the given name to the language, where
+/// translations exist.
+fn translate(set: &str, emojis: &mut [&'static Emoji], language: &str) {
+    let Some(descriptions) = locale::descriptions(set, language) else {
+        return;
+    };
+
+    for emoji in emojis {
+        let translation = descriptions.iter().find(|(code, _)| *code == emoji.code);
+        if let Some((_, description)) = translation {
+            *emoji = emoji.translated(description);
+        }
+    }
+}
+
+/// The emojis merged with the ones from a database file.
+///
+/// Emojis with the same code as an existing one replace it, and the others are appended. If the
+/// file has `"replace": true`, its emojis replace the whole set instead. The emojis with one of the
+/// codes listed in `hide` are then removed, and the ones listed in `tags` and `weights` are given
+/// these extra tags and weights.
+fn merge(emojis: &[&'static Emoji], file: &DatabaseFile<&'static Emoji>) -> Vec<&'static Emoji> {
+    let mut merged = if file.replace {
+        file.gitmojis.clone()
+    } else {
+        let mut merged = emojis.to_vec();
+        for &emoji in &file.gitmojis {
+            match merged.iter_mut().find(|e| e.code == emoji.code) {
+                Some(existing) => *existing = emoji,
+                None => merged.push(emoji),
+            }
+        }
+
+        merged
+    };
+    merged.retain(|emoji| !file.hide.iter().any(|code| code == emoji.code));
+    for emoji in &mut merged {
+        if let Some(tags) = file.tags.get(emoji.code) {
+            *emoji = emoji.with_tags(tags);
+        }
+        if let Some(&weight) = file.weights.get(emoji.code) {
+            *emoji = emoji.with_weight(weight);
+        }
+    }
+
+    merged
+}
+
+/// The index of the set of the given name.
+fn position(sets: &[EmojiSet], name: &str) -> anyhow::Result<usize> {
+    let Some(position) = sets.iter().position(|set| set.name == name) else {
+        let names: Vec<_> = sets.iter().map(|set| set.name.as_str()).collect();
+        bail!(
+            "Unknown emoji set `{name}`, available sets are: {}",
+            names.join(", ")
+        );
+    };
+
+    Ok(position)
+}
+
+/// The custom sets defined by the JSON files of the given directory, sorted by name.
+fn custom_sets(dir: &Path) -> anyhow::Result<Vec<EmojiSet>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read `{}`", dir.display())),
+    };
+
+    let mut sets = Vec::new();
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("Failed to read `{}`", dir.display()))?
+            .path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let Some(file) = DatabaseFile::read(&path)? else {
+            continue;
+        };
+
+        let file = file.leak();
+        sets.push(EmojiSet::new(
+            format!("{CUSTOM_SET_PREFIX}{name}"),
+            move || merge(&[], &file),
+        ));
+    }
+    sets.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(sets)
+}
+
+/// Check that the JSON is a valid emoji database, following the gitmoji schema.
+pub fn validate(json: &str) -> anyhow::Result<()> {
+    DatabaseFile::parse(json)?;
+
+    Ok(())
+}
+
+/// An emoji database file, following the gitmoji schema.
+#[derive(Deserialize)]
+struct DatabaseFile<E = EmojiEntry> {
+    // Not `default`, which would require `E: Default`.
+    #[serde(default = "Vec::new")]
+    gitmojis: Vec<E>,
+    /// Replace the embedded emojis instead of merging with them.
+    #[serde(default)]
+    replace: bool,
+    /// Codes of the emojis to leave out.
+    #[serde(default)]
+    hide: Vec<String>,
+    /// Extra tags to add to the emojis, by code.
+    #[serde(default)]
+    tags: HashMap<String, Vec<String>>,
+    /// Weights to give to the emojis, by code.
+    #[serde(default)]
+    weights: HashMap<String, i32>,
+}
+
+impl DatabaseFile {
+    /// Read the file, if it exists.
+    fn read(path: &Path) -> anyhow::Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read `{}`", path.display()))
+            }
+        };
+
+        Self::parse(&json)
+            .map(Some)
+            .with_context(|| format!("Invalid emoji database `{}`", path.display()))
+    }
+
+    fn parse(json: &str) -> anyhow::Result<Self> {
+        Self::from_value(serde_json::from_str(json)?)
+    }
+
+    fn from_value(value: serde_json::Value) -> anyhow::Result<Self> {
+        let problems = schema::validate(&value);
+        if !problems.is_empty() {
+            let problems: Vec<_> = problems.iter().map(ToString::to_string).collect();
+            bail!("{}", problems.join("\n"));
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Create the emojis of the file, so they can be shared by all the sets.
+    fn leak(self) -> DatabaseFile<&'static Emoji> {
+        DatabaseFile {
+            gitmojis: self.gitmojis.into_iter().map(EmojiEntry::leak).collect(),
+            replace: self.replace,
+            hide: self.hide,
+            tags: self.tags,
+            weights: self.weights,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmojiEntry {
+    code: String,
+    description: String,
+    emoji: String,
+    /// Derived from the emoji if missing.
+    entity: Option<String>,
+    /// Derived from the code if missing.
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(alias = "group")]
+    category: Option<String>,
+    semver: Option<Semver>,
+    #[serde(default)]
+    weight: i32,
+}
+
+impl EmojiEntry {
+    fn leak(self) -> &'static Emoji {
+        let entity = self.entity.unwrap_or_else(|| {
+            let codepoints: Vec<_> = self
+                .emoji
+                .chars()
+                .map(|c| format!("{:X}", u32::from(c)))
+                .collect();
+
+            format!("&#x{};", codepoints.join("-"))
+        });
+        let name = self
+            .name
+            .unwrap_or_else(|| self.code.trim_matches(':').to_owned());
+
+        Emoji::leak(
+            [
+                self.code,
+                self.description,
+                self.emoji,
+                entity,
+                name,
+                self.tags.join(" "),
+                self.category.unwrap_or_default(),
+            ],
+            self.semver,
+            self.weight,
+        )
+    }
+}
+
+/// The embedded emoji sets, the first one being the default.
+static BUILTIN_SETS: [(&str, &Compressed); 4] = [
+    (DEFAULT_SET, &EMOJIS),
+    (GITMOJI_SET, &GITMOJI),
+    ("devmoji", &DEVMOJI),
+    ("conventional", &CONVENTIONAL),
+];
+pub const DEFAULT_SET: &str = "unicode";
+const GITMOJI_SET: &str = "gitmoji";
+/// The sets whose codes are conventional commit types.
+const COMMIT_TYPE_SETS: [&str; 2] = ["devmoji", "conventional"];
+const CUSTOM_SETS_DIR: &str = "sets";
+const CUSTOM_SET_PREFIX: &str = "custom-";
+const USER_DATABASE_FILE: &str = "emojis.json";
+const REPO_DATABASE_FILE: &str = ".gimoji/emojis.json";
+pub const REPO_DATABASE_GIT_CONFIG: &str = "gimoji.emojis";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        emoji::Field,
+        matcher::MatchMode,
+        query::{Query, SearchOptions},
+        synonyms::Synonyms,
+    };
+
+    #[test]
+    fn search_translated_descriptions() {
+        let mut emojis: Vec<_> = GITMOJI.emojis().iter().collect();
+        translate(GITMOJI_SET, &mut emojis, "de");
+        let bug = emojis.iter().find(|emoji| emoji.code == ":bug:").unwrap();
+        assert_eq!(bug.description, "Einen Fehler beheben.");
+        assert_eq!(bug.untranslated.unwrap().description, "Fix a bug.");
+        assert_eq!(bug.tags, Emoji::by_code(":bug:").unwrap().tags);
+
+        let options = SearchOptions {
+            mode: MatchMode::Substring,
+            synonyms: Synonyms::builtin(),
+            case_sensitive: false,
+            typo_tolerance: 1,
+            fields: Field::ALL.into(),
+        };
+        let search = |text: &str| -> Vec<&str> {
+            let query = Query::parse(text, &options).unwrap();
+            query
+                .filter(&emojis)
+                .emojis(&emojis)
+                .map(|emoji| emoji.code)
+                .collect()
+        };
+        // In German, in English, or both.
+        assert!(search("fehler").contains(&":bug:"));
+        assert_eq!(search("desc:fix desc:bug"), [":bug:"]);
+        assert_eq!(search("fehler beheben bug"), [":bug:"]);
+        assert!(search("!desc:fehler").iter().all(|&code| code != ":bug:"));
+        assert!(search("!desc:bug").iter().all(|&code| code != ":bug:"));
+        // With typos.
+        assert!(search("fehlr").contains(&":bug:"));
+        assert!(search("remvoe").contains(&":fire:"));
+        // Only the translated description is shown, so only it is highlighted.
+        let query = Query::parse("bug", &options).unwrap();
+        assert!(query.highlights(bug, Field::Description).is_empty());
+        let query = Query::parse("fehler", &options).unwrap();
+        assert_eq!(query.highlights(bug, Field::Description), vec![6..12]);
+    }
+}
@@ -1,4 +1,14 @@
-use regex::Regex;
+use std::{
+    borrow::Cow, cmp::Reverse, collections::HashMap, convert::Infallible, ops::Range, ptr,
+    str::FromStr, sync::OnceLock,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{matcher::Matcher, normalize::normalize};
+
+use std::{array, iter};
 
 #[derive(Debug)]
 pub struct Emoji {
@@ -7,16 +17,618 @@ pub struct Emoji {
     pub emoji: &'static str,
     pub entity: &'static str,
     pub name: &'static str,
+    /// Extra search keywords, separated by spaces.
+    pub tags: &'static str,
+    /// The group the emoji belongs to, e.g. `smileys-emotion` or `deps`. Empty if unknown.
+    pub category: &'static str,
+    /// The kind of version bump a commit with this emoji calls for, if any.
+    pub semver: Option<Semver>,
+    /// How much to push the emoji up the list, or down if negative. Emojis are listed by
+    /// decreasing weight, then in their usual order.
+    pub weight: i32,
+    /// The normalized, lowercase fields, indexed by [`Field`].
+    pub normalized: [&'static str; 7],
+    /// The emoji this one is a translation of, whose description is searched too.
+    pub untranslated: Option<&'static Emoji>,
 }
 
 impl Emoji {
-    pub fn contains(&self, pattern: &Regex) -> bool {
-        pattern.is_match(self.code)
-            || pattern.is_match(self.description)
-            || pattern.is_match(self.emoji)
-            || pattern.is_match(self.entity)
-            || pattern.is_match(self.name)
+    /// Create an emoji at runtime, e.g. from a user-provided database.
+    ///
+    /// The fields are given in the order of [`Field`]. Emojis are expected to live until the
+    /// process exits, so the strings are simply leaked.
+    pub fn leak(fields: [String; 7], semver: Option<Semver>, weight: i32) -> &'static Self {
+        Box::leak(Box::new(Self::new(fields, semver, weight)))
+    }
+
+    fn new(fields: [String; 7], semver: Option<Semver>, weight: i32) -> Self {
+        let fields = fields.map(|f| &*f.leak());
+        let normalized = array::from_fn(|i| &*normalize(fields[i]).to_lowercase().leak());
+        let [code, description, emoji, entity, name, tags, category] = fields;
+
+        Self {
+            code,
+            description,
+            emoji,
+            entity,
+            name,
+            tags,
+            category,
+            semver,
+            weight,
+            normalized,
+            untranslated: None,
+        }
+    }
+
+    /// A copy of the emoji with other fields and weight, still a translation of the same emoji.
+    fn copy(&self, fields: [String; 7], weight: i32) -> &'static Self {
+        Box::leak(Box::new(Self {
+            untranslated: self.untranslated,
+            ..Self::new(fields, self.semver, weight)
+        }))
+    }
+
+    /// A copy of the emoji with some more tags.
+    pub fn with_tags(&self, tags: &[String]) -> &'static Self {
+        let tags = self
+            .tags
+            .split_whitespace()
+            .chain(tags.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut fields = Field::ALL.map(|field| self.field(field).to_owned());
+        fields[Field::Tags as usize] = tags;
+
+        self.copy(fields, self.weight)
+    }
+
+    /// A copy of the emoji with another weight.
+    pub fn with_weight(&self, weight: i32) -> &'static Self {
+        let fields = Field::ALL.map(|field| self.field(field).to_owned());
+
+        self.copy(fields, weight)
+    }
+
+    /// A copy of the emoji with a translated description, which searches match along with the
+    /// original one.
+    pub fn translated(&'static self, description: &str) -> &'static Self {
+        let mut fields = Field::ALL.map(|field| self.field(field).to_owned());
+        fields[Field::Description as usize] = description.to_owned();
+
+        Box::leak(Box::new(Self {
+            untranslated: Some(self.untranslated.unwrap_or(self)),
+            ..Self::new(fields, self.semver, self.weight)
+        }))
+    }
+
+    /// Whether any of the given normalized fields matches.
+    pub fn contains(
+        &self,
+        fields: &[Field],
+        matcher: &(impl Matcher + ?Sized),
+        case_sensitive: bool,
+    ) -> bool {
+        fields.iter().any(|field| {
+            self.searched_texts(*field, case_sensitive)
+                .any(|text| matcher.is_match(&text))
+        })
+    }
+
+    /// The byte ranges of the matches in the (original, not normalized) field.
+    pub fn match_ranges(
+        &self,
+        field: Field,
+        matcher: &(impl Matcher + ?Sized),
+        case_sensitive: bool,
+    ) -> Vec<Range<usize>> {
+        let ranges = matcher.find_ranges(&self.searched(field, case_sensitive));
+        let original = self.field(field);
+        if ranges.is_empty() || original.is_ascii() {
+            // Lowercasing ASCII doesn't move anything around.
+            return ranges;
+        }
+
+        // Where each character of the original text ended up in the normalized one.
+        let mut spans = Vec::new();
+        let mut normalized_len = 0;
+        for (idx, c) in original.char_indices() {
+            let mut buf = [0; 4];
+            let normalized = normalize(c.encode_utf8(&mut buf));
+            let len = if case_sensitive {
+                normalized.len()
+            } else {
+                normalized.to_lowercase().len()
+            };
+            spans.push((
+                idx..idx + c.len_utf8(),
+                normalized_len..normalized_len + len,
+            ));
+            normalized_len += len;
+        }
+
+        ranges
+            .into_iter()
+            .filter_map(|range| {
+                let mut overlapping = spans
+                    .iter()
+                    .filter(|(_, n)| n.start < range.end && range.start < n.end);
+                let first = overlapping.next()?;
+                let last = overlapping.next_back().unwrap_or(first);
+
+                Some(first.0.start..last.0.end)
+            })
+            .collect()
+    }
+
+    /// The variants of this emoji among the given ones, i.e. the ones only differing by skin tone
+    /// modifiers or variation selectors, including this one.
+    ///
+    /// Emojis that are themselves a skin tone variant have no other variants.
+    pub fn variants<'e>(&'e self, emojis: &[&'e Emoji]) -> Vec<&'e Emoji> {
+        if self.emoji.chars().any(is_skin_tone) {
+            return vec![self];
+        }
+
+        let base = |emoji: &str| {
+            emoji
+                .chars()
+                .filter(|&c| !is_skin_tone(c) && !VARIATION_SELECTORS.contains(&c))
+                .collect::<String>()
+        };
+        let own_base = base(self.emoji);
+        let variants: Vec<_> = emojis
+            .iter()
+            .copied()
+            .filter(|emoji| !std::ptr::eq(*emoji, self) && base(emoji.emoji) == own_base)
+            .collect();
+
+        [self].into_iter().chain(variants).collect()
+    }
+
+    /// The Unicode codepoints of the emoji, e.g. `U+1F44D U+1F3FD`.
+    pub fn codepoints(&self) -> String {
+        let codepoints: Vec<_> = self
+            .emoji
+            .chars()
+            .map(|c| format!("U+{:04X}", u32::from(c)))
+            .collect();
+
+        codepoints.join(" ")
+    }
+
+    /// The normalized, lowercase text of the field, which is what searches match against.
+    pub fn normalized(&self, field: Field) -> &'static str {
+        self.normalized[field as usize]
+    }
+
+    /// The text of the field searches match against: the normalized one, only lowercase if the
+    /// search isn't case-sensitive.
+    pub fn searched(&self, field: Field, case_sensitive: bool) -> Cow<'static, str> {
+        if case_sensitive {
+            normalize(self.field(field))
+        } else {
+            Cow::Borrowed(self.normalized(field))
+        }
+    }
+
+    /// The texts of the field searches match against, see [`Self::searched`]: for the description
+    /// of a translated emoji, both the translated and the original one.
+    pub fn searched_texts(
+        &self,
+        field: Field,
+        case_sensitive: bool,
+    ) -> impl Iterator<Item = Cow<'static, str>> + '_ {
+        let untranslated = self.untranslated.filter(|_| field == Field::Description);
+
+        iter::once(self.searched(field, case_sensitive))
+            .chain(untranslated.map(move |emoji| emoji.searched(field, case_sensitive)))
+    }
+
+    pub fn field(&self, field: Field) -> &'static str {
+        match field {
+            Field::Code => self.code,
+            Field::Description => self.description,
+            Field::Emoji => self.emoji,
+            Field::Entity => self.entity,
+            Field::Name => self.name,
+            Field::Tags => self.tags,
+            Field::Category => self.category,
+        }
+    }
+
+    /// The embedded emoji with the code, e.g. `:bug:`, from the first embedded set that has it.
+    pub fn by_code(code: &str) -> Option<&'static Self> {
+        EMBEDDED_SETS
+            .iter()
+            .find_map(|set| set.get(set.by_code, code))
+    }
+
+    /// The embedded emoji, e.g. `🐛`, from the first embedded set that has it.
+    pub fn by_char(emoji: &str) -> Option<&'static Self> {
+        EMBEDDED_SETS
+            .iter()
+            .find_map(|set| set.get(set.by_char, emoji))
+    }
+
+    /// The longest of the emojis the text starts with, if any, among those that are embedded.
+    ///
+    /// Every embedded emoji the text starts with is tried, as a shorter one may be in the emojis
+    /// while a longer one is only in another set, e.g. `⚡` and `⚡️`.
+    pub fn by_prefix(text: &str, emojis: &[&'static Self]) -> Option<&'static Self> {
+        let mut prefixes: Vec<_> = EMBEDDED_SETS
+            .iter()
+            .flat_map(|set| set.prefixes(text))
+            .collect();
+        prefixes.sort_by_key(|prefix| Reverse(prefix.len()));
+
+        prefixes
+            .into_iter()
+            .find_map(|prefix| emojis.iter().find(|emoji| emoji.emoji == prefix))
+            .copied()
+    }
+
+    /// Whether this is one of the embedded emojis, rather than one created at runtime.
+    pub fn is_embedded(&self) -> bool {
+        // The emojis of the sets that weren't decompressed yet can't be around.
+        EMBEDDED_SETS.iter().any(|set| {
+            set.emojis
+                .decompressed()
+                .is_some_and(|emojis| emojis.as_ptr_range().contains(&(self as *const Self)))
+        })
+    }
+}
+
+/// The emojis of an embedded set, compressed at build time and decompressed at first use.
+pub struct Compressed {
+    data: &'static [u8],
+    emojis: OnceLock<Box<[Emoji]>>,
+}
+
+impl Compressed {
+    const fn new(data: &'static [u8]) -> Self {
+        Self {
+            data,
+            emojis: OnceLock::new(),
+        }
+    }
+
+    pub fn emojis(&'static self) -> &'static [Emoji] {
+        self.emojis.get_or_init(|| self.decompress())
+    }
+
+    /// The emojis, if they were decompressed already.
+    fn decompressed(&'static self) -> Option<&'static [Emoji]> {
+        self.emojis.get().map(|emojis| &**emojis)
+    }
+
+    /// Read the emojis from the records written by the build script, whose strings they borrow.
+    fn decompress(&self) -> Box<[Emoji]> {
+        let data = miniz_oxide::inflate::decompress_to_vec(self.data)
+            .expect("The embedded emojis are valid deflate data");
+        let text = String::from_utf8(data)
+            .expect("The embedded emojis are valid UTF-8")
+            .leak();
+
+        text.split(RECORD_SEPARATOR)
+            .map(|record| {
+                let mut fields = record.split(FIELD_SEPARATOR);
+                let mut next = || fields.next().expect("The embedded emojis have all fields");
+                let [code, description, emoji, entity, name, tags, category] =
+                    array::from_fn(|_| next());
+                let semver = match next() {
+                    "major" => Some(Semver::Major),
+                    "minor" => Some(Semver::Minor),
+                    "patch" => Some(Semver::Patch),
+                    _ => None,
+                };
+                let weight = next().parse().expect("The embedded weights are integers");
+
+                Emoji {
+                    code,
+                    description,
+                    emoji,
+                    entity,
+                    name,
+                    tags,
+                    category,
+                    semver,
+                    weight,
+                    normalized: array::from_fn(|_| next()),
+                    untranslated: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// An embedded set of emojis, along with their indices sorted by code and by emoji, and in the
+/// orders of [`Order`], generated at build time.
+struct EmbeddedSet {
+    emojis: &'static Compressed,
+    by_code: &'static [(&'static str, u16)],
+    by_char: &'static [(&'static str, u16)],
+    code_order: &'static [u16],
+    category_order: &'static [u16],
+}
+
+impl EmbeddedSet {
+    /// The emojis in the order, if it was computed at build time and they're exactly the ones of
+    /// this set in the database order.
+    fn sorted(&self, emojis: &[&'static Emoji], order: Order) -> Option<Box<[&'static Emoji]>> {
+        let all = self.emojis.decompressed()?;
+        let unchanged =
+            all.len() == emojis.len() && all.iter().zip(emojis).all(|(a, &b)| ptr::eq(a, b));
+        if !unchanged {
+            return None;
+        }
+
+        let indices = match order {
+            Order::Code => self.code_order,
+            Order::Category => self.category_order,
+            _ => return None,
+        };
+
+        Some(indices.iter().map(|&i| &all[usize::from(i)]).collect())
+    }
+
+    fn get(&self, table: &[(&str, u16)], key: &str) -> Option<&'static Emoji> {
+        let i = table.binary_search_by_key(&key, |&(key, _)| key).ok()?;
+
+        Some(&self.emojis.emojis()[usize::from(table[i].1)])
+    }
+
+    /// The emojis of the set the text starts with, found in the table so that the set isn't
+    /// decompressed.
+    fn prefixes(&self, text: &str) -> Vec<&'static str> {
+        let mut prefixes = Vec::new();
+        for (start, c) in text.char_indices() {
+            let prefix = &text[..start + c.len_utf8()];
+            match self.by_char.binary_search_by_key(&prefix, |&(key, _)| key) {
+                Ok(i) => prefixes.push(self.by_char[i].0),
+                // Longer emojis starting with the prefix are sorted right after it.
+                Err(i)
+                    if self
+                        .by_char
+                        .get(i)
+                        .is_some_and(|(key, _)| key.starts_with(prefix)) => {}
+                Err(_) => break,
+            }
+        }
+
+        prefixes
+    }
+}
+
+/// The embedded sets, in the order of the database.
+static EMBEDDED_SETS: [EmbeddedSet; 4] = [
+    EmbeddedSet {
+        emojis: &EMOJIS,
+        by_code: EMOJIS_BY_CODE,
+        by_char: EMOJIS_BY_CHAR,
+        code_order: EMOJIS_CODE_ORDER,
+        category_order: EMOJIS_CATEGORY_ORDER,
+    },
+    EmbeddedSet {
+        emojis: &GITMOJI,
+        by_code: GITMOJI_BY_CODE,
+        by_char: GITMOJI_BY_CHAR,
+        code_order: GITMOJI_CODE_ORDER,
+        category_order: GITMOJI_CATEGORY_ORDER,
+    },
+    EmbeddedSet {
+        emojis: &DEVMOJI,
+        by_code: DEVMOJI_BY_CODE,
+        by_char: DEVMOJI_BY_CHAR,
+        code_order: DEVMOJI_CODE_ORDER,
+        category_order: DEVMOJI_CATEGORY_ORDER,
+    },
+    EmbeddedSet {
+        emojis: &CONVENTIONAL,
+        by_code: CONVENTIONAL_BY_CODE,
+        by_char: CONVENTIONAL_BY_CHAR,
+        code_order: CONVENTIONAL_CODE_ORDER,
+        category_order: CONVENTIONAL_CATEGORY_ORDER,
+    },
+];
+
+fn is_skin_tone(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+const VARIATION_SELECTORS: [char; 2] = ['\u{FE0E}', '\u{FE0F}'];
+
+/// A searchable field of an [`Emoji`].
+///
+/// The order must match the one of the normalized fields generated by the build script.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Field {
+    Code = 0,
+    #[value(alias = "desc")]
+    #[serde(alias = "desc")]
+    Description = 1,
+    Emoji = 2,
+    Entity = 3,
+    Name = 4,
+    #[value(alias = "tag")]
+    #[serde(alias = "tag")]
+    Tags = 5,
+    #[value(alias = "cat")]
+    #[serde(alias = "cat")]
+    Category = 6,
+}
+
+impl Field {
+    pub const ALL: [Self; 7] = [
+        Self::Code,
+        Self::Description,
+        Self::Emoji,
+        Self::Entity,
+        Self::Name,
+        Self::Tags,
+        Self::Category,
+    ];
+
+    /// The field named by a `<prefix>:` in search queries.
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "code" => Some(Self::Code),
+            "desc" | "description" => Some(Self::Description),
+            "emoji" => Some(Self::Emoji),
+            "entity" => Some(Self::Entity),
+            "name" => Some(Self::Name),
+            "tag" | "tags" => Some(Self::Tags),
+            "cat" | "category" => Some(Self::Category),
+            _ => None,
+        }
+    }
+}
+
+/// How selected emojis are written, e.g. in commit messages.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum EmojiFormat {
+    /// The emoji itself, e.g. `✨`.
+    #[default]
+    Emoji,
+    /// The code of the emoji, e.g. `:sparkles:`.
+    Code,
+    /// A template where `{<field>}` is replaced with that field of the emoji, e.g.
+    /// `{emoji} ({name})`.
+    Template(String),
+}
+
+impl EmojiFormat {
+    /// The emoji, in this format.
+    pub fn format(&self, emoji: &Emoji) -> String {
+        match self {
+            Self::Emoji => emoji.emoji.to_owned(),
+            Self::Code => emoji.code.to_owned(),
+            Self::Template(template) => Field::ALL.iter().fold(template.clone(), |text, field| {
+                let name = field.to_possible_value().expect("Fields are all named");
+                text.replace(&format!("{{{}}}", name.get_name()), emoji.field(*field))
+            }),
+        }
+    }
+}
+
+impl FromStr for EmojiFormat {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "emoji" => Self::Emoji,
+            "code" => Self::Code,
+            template => Self::Template(template.to_owned()),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for EmojiFormat {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let format = String::deserialize(deserializer)?;
+
+        Ok(format.parse().unwrap_or_else(|e| match e {}))
+    }
+}
+
+impl Serialize for EmojiFormat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            Self::Emoji => "emoji",
+            Self::Code => "code",
+            Self::Template(template) => template,
+        })
+    }
+}
+
+/// A kind of semantic version bump, from the biggest to the smallest.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Semver {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// The order emojis are listed in.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// The order of the emoji database.
+    #[default]
+    Database,
+    /// Alphabetical order of the codes.
+    Code,
+    /// Alphabetical order of the categories, the emojis of each staying in the database order.
+    Category,
+    /// The emojis calling for the biggest version bumps first, as `gimoji bump` maps them, then the
+    /// others, each staying in the database order.
+    Semver,
+    /// The emojis selected the most often first, according to the history, then the others, each
+    /// staying in the database order.
+    Usage,
+}
+
+impl Order {
+    /// The emojis, in this order, given how many times each was selected by code.
+    ///
+    /// Heavier emojis come first either way, the database being sorted by weight already. The
+    /// embedded sets, if unchanged, come with their orders by code and category computed at build
+    /// time.
+    pub fn sorted(
+        self,
+        emojis: &[&'static Emoji],
+        uses: &HashMap<String, usize>,
+    ) -> Box<[&'static Emoji]> {
+        let mut sorted: Box<[_]> = emojis.into();
+        let field = match self {
+            Self::Database => return sorted,
+            Self::Code => Field::Code,
+            Self::Category => Field::Category,
+            Self::Semver => {
+                // `None` sorts before the semvers.
+                sorted.sort_by_key(|emoji| {
+                    (Reverse(emoji.weight), emoji.semver.is_none(), emoji.semver)
+                });
+                return sorted;
+            }
+            Self::Usage => {
+                let uses = |emoji: &Emoji| uses.get(emoji.code).copied().unwrap_or_default();
+                sorted.sort_by_key(|emoji| (Reverse(emoji.weight), Reverse(uses(emoji))));
+                return sorted;
+            }
+        };
+        if let Some(sorted) = EMBEDDED_SETS
+            .iter()
+            .find_map(|set| set.sorted(emojis, self))
+        {
+            return sorted;
+        }
+
+        sorted.sort_by_key(|emoji| (Reverse(emoji.weight), emoji.normalized(field)));
+
+        sorted
     }
 }
 
+/// The separators of the fields of each emoji, and of the emojis, in the compressed records.
+const FIELD_SEPARATOR: char = '\x1f';
+const RECORD_SEPARATOR: char = '\x1e';
+
 include!(concat!(env!("OUT_DIR"), "/emojis.rs"));
@@ -0,0 +1,59 @@
+use std::{fmt, path::PathBuf};
+
+/// Failures callers may want to react to specifically.
+///
+/// These are returned inside [`anyhow::Error`]s like all the others, and found back with
+/// [`anyhow::Error::downcast_ref`], context included.
+#[derive(Debug)]
+pub enum Error {
+    /// There's already a commit message hook, at the given path, and it's not to be overwritten.
+    HookAlreadyExists(PathBuf),
+    /// The current directory is not in a git repository.
+    NotAGitRepo,
+    /// The current directory is not in a Mercurial repository.
+    NotAMercurialRepo,
+    /// There's no terminal to show the picker on.
+    NoTty,
+    /// The system clipboard can't be accessed, for the given reason.
+    ClipboardUnavailable(String),
+    /// The commit message breaks the given number of lint rules.
+    LintFailed(usize),
+    /// The picker was exited without selecting an emoji.
+    Cancelled,
+}
+
+impl Error {
+    /// The exit code of the command line tool for this failure, 1 being for all the others.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::HookAlreadyExists(_) => 3,
+            Self::NotAGitRepo | Self::NotAMercurialRepo => 4,
+            Self::NoTty => 5,
+            Self::ClipboardUnavailable(_) => 6,
+            Self::LintFailed(_) => 7,
+            // As if interrupted with Ctrl+C.
+            Self::Cancelled => 130,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HookAlreadyExists(path) => write!(f, "`{}` already exists", path.display()),
+            Self::NotAGitRepo => f.write_str("Not in a git repository"),
+            Self::NotAMercurialRepo => f.write_str("Not in a Mercurial repository"),
+            Self::NoTty => f.write_str("No terminal to select an emoji in"),
+            Self::ClipboardUnavailable(reason) => {
+                write!(f, "Failed to access the clipboard: {reason}")
+            }
+            Self::LintFailed(1) => f.write_str("The commit message breaks a lint rule"),
+            Self::LintFailed(count) => {
+                write!(f, "The commit message breaks {count} lint rules")
+            }
+            Self::Cancelled => f.write_str("No emoji selected"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,217 @@
+use std::{
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+
+/// The root of the working tree the current directory is in, if any.
+pub fn repo_root() -> Option<PathBuf> {
+    run(&["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// The absolute path of the git directory of the repository the current directory is in, if any.
+pub fn git_dir() -> Option<PathBuf> {
+    run(&["rev-parse", "--absolute-git-dir"]).map(PathBuf::from)
+}
+
+/// The directory of the hooks of the repository the current directory is in, if any, which may be
+/// shared by worktrees or set elsewhere by `core.hooksPath`.
+pub fn hooks_dir() -> Option<PathBuf> {
+    let path = run(&["rev-parse", "--git-path", "hooks"])?;
+
+    // Relative to the current directory.
+    Some(env::current_dir().ok()?.join(path))
+}
+
+/// The short name of the branch checked out, if any.
+pub fn current_branch() -> Option<String> {
+    run(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+}
+
+/// The paths of the changes staged for the next commit, relative to the root of the repository.
+pub fn staged_paths() -> Vec<String> {
+    // NUL-separated, as git quotes unusual paths otherwise.
+    let Some(output) = run(&["diff", "--cached", "--name-only", "-z"]) else {
+        return Vec::new();
+    };
+
+    output.split_terminator('\0').map(str::to_owned).collect()
+}
+
+/// The diff of the changes staged for the next commit, without context lines.
+pub fn staged_diff() -> Option<String> {
+    run(&[
+        "diff",
+        "--cached",
+        "--no-color",
+        "--no-ext-diff",
+        "--unified=0",
+    ])
+}
+
+/// The full message of the `HEAD` commit, if there's one.
+pub fn head_message() -> Option<String> {
+    run(&["log", "-1", "--format=%B", "HEAD"])
+}
+
+/// Replace the message of the `HEAD` commit, leaving its changes and the staged ones alone.
+///
+/// The hooks are skipped, as the message is final, and gimoji's would add an emoji to it again.
+pub fn amend_message(message: &str) -> anyhow::Result<()> {
+    let mut git = Command::new("git")
+        .args([
+            // A directory without hooks, which git for Windows understands too.
+            "-c",
+            "core.hooksPath=/dev/null",
+            "commit",
+            "--amend",
+            "--only",
+            "--allow-empty",
+            "--quiet",
+            "--file=-",
+        ])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+    let mut stdin = git.stdin.take().expect("stdin is piped");
+    stdin
+        .write_all(message.as_bytes())
+        .context("Failed to give git the commit message")?;
+    drop(stdin);
+    let output = git.wait_with_output().context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to amend the commit: {}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+
+    Ok(())
+}
+
+/// The most recent tag reachable from `HEAD`, if any.
+pub fn last_tag() -> Option<String> {
+    run(&["describe", "--tags", "--abbrev=0"])
+}
+
+/// The abbreviated hashes and subjects of the commits reachable from `HEAD`, but not from the
+/// given revision if any, newest first.
+pub fn log_subjects(since: Option<&str>) -> Vec<(String, String)> {
+    let range = match since {
+        Some(since) => format!("{since}..HEAD"),
+        None => "HEAD".to_owned(),
+    };
+    // Without commits, there's no `HEAD` to log.
+    let Some(output) = run(&["log", "--format=%h%x00%s", &range]) else {
+        return Vec::new();
+    };
+
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .map(|(hash, subject)| (hash.to_owned(), subject.to_owned()))
+        .collect()
+}
+
+/// The abbreviated hashes and subjects of the commits a rebase of `HEAD` onto the given revision
+/// picks, i.e. the ones since then except merges, oldest first.
+pub fn rebased_subjects(onto: &str) -> Vec<(String, String)> {
+    let range = format!("{onto}..HEAD");
+    let Some(output) = run(&[
+        "log",
+        "--reverse",
+        "--no-merges",
+        "--format=%h%x00%s",
+        &range,
+    ]) else {
+        return Vec::new();
+    };
+
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .map(|(hash, subject)| (hash.to_owned(), subject.to_owned()))
+        .collect()
+}
+
+/// Rebase `HEAD` interactively onto the given revision, with the given todo list rather than the
+/// one git would write, and the given command as the editor of the commit messages to reword.
+pub fn rebase_with_todo(onto: &str, todo: &Path, editor: &str) -> anyhow::Result<()> {
+    let status = Command::new("git")
+        .args(["rebase", "--interactive", onto])
+        // Run by the shell, with the todo list git wrote as its argument.
+        .env("GIT_SEQUENCE_EDITOR", format!("cp \"{}\"", todo.display()))
+        .env("GIT_EDITOR", editor)
+        .status()
+        .context("Failed to run git")?;
+    if !status.success() {
+        bail!("`git rebase` exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// The value of a git config key, if set.
+pub fn config_get(key: &str) -> Option<String> {
+    run(&["config", "--get", key])
+}
+
+/// The value of a git config key in the given config file, e.g. `--global`, if set.
+pub fn config_get_in(file: &str, key: &str) -> Option<String> {
+    run(&["config", file, "--get", key])
+}
+
+/// Set a git config key in the given config file, e.g. `--global`.
+///
+/// Unlike reading it, this is an error if git fails.
+pub fn config_set_in(file: &str, key: &str, value: &str) -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .args(["config", file, key, value])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to set `{key}`: {}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+
+    Ok(())
+}
+
+/// The keys and values of the git config starting with the given prefix.
+pub fn config_get_all(prefix: &str) -> Vec<(String, String)> {
+    let regex = format!("^{}", prefix.replace('.', "\\."));
+    // NUL-separated, as values can span several lines.
+    let Some(output) = run(&["config", "--null", "--get-regexp", &regex]) else {
+        return Vec::new();
+    };
+
+    output
+        .split_terminator('\0')
+        .map(|entry| match entry.split_once('\n') {
+            Some((key, value)) => (key.to_owned(), value.to_owned()),
+            // Keys set without a value, which git takes as `true`.
+            None => (entry.to_owned(), "true".to_owned()),
+        })
+        .collect()
+}
+
+/// Run git with the given arguments, returning its trimmed output if it succeeded.
+///
+/// Failures, including git not being installed, are treated as "nothing to report" since git
+/// integration is always optional.
+fn run(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|stdout| stdout.trim_end().to_owned())
+}
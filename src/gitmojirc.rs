@@ -0,0 +1,54 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{emoji::EmojiFormat, git, paths};
+
+/// The settings of [gitmoji-cli](https://github.com/carloscuesta/gitmoji-cli) that gimoji honors,
+/// so that its users keep their setup.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GitmojiRc {
+    pub emoji_format: Option<EmojiFormat>,
+    /// Whether to ask for the scope of commits, off by default.
+    pub scope_prompt: Option<bool>,
+    /// Whether to ask for the body of commit messages, on by default.
+    pub message_prompt: Option<bool>,
+    /// Whether to capitalize the first letter of commit titles, on by default.
+    pub capitalize_title: Option<bool>,
+    /// Where to fetch the gitmoji database from, instead of gitmoji.dev.
+    pub gitmojis_url: Option<String>,
+}
+
+impl GitmojiRc {
+    /// Load the `.gitmojirc.json` file at the root of the repository, or the one in the home
+    /// directory otherwise, falling back to gitmoji-cli's defaults if there are none.
+    pub fn load() -> anyhow::Result<Self> {
+        let dirs = [git::repo_root(), paths::home_dir()];
+        for dir in dirs.into_iter().flatten() {
+            if let Some(rc) = Self::read(&dir.join(GITMOJIRC_FILE))? {
+                return Ok(rc);
+            }
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Read the file, if it exists.
+    fn read(path: &Path) -> anyhow::Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read `{}`", path.display()))
+            }
+        };
+
+        serde_json::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("Invalid gitmoji-cli config `{}`", path.display()))
+    }
+}
+
+const GITMOJIRC_FILE: &str = ".gitmojirc.json";
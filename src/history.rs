@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{emoji::Emoji, git};
+
+/// The emojis selected in the picker, oldest first.
+///
+/// These are kept in `history.json` in the data directory, which can be changed in the config.
+#[derive(Deserialize, Serialize, Default)]
+pub struct History {
+    pub entries: Vec<Entry>,
+}
+
+/// A selection of an emoji.
+#[derive(Deserialize, Serialize)]
+pub struct Entry {
+    pub code: String,
+    pub emoji: String,
+    /// When the emoji was selected, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The root of the repository the emoji was selected in, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<PathBuf>,
+}
+
+/// An emoji taken from the history rather than selected in the picker.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preselect {
+    /// The most recently selected emoji.
+    Last,
+}
+
+/// How much history is kept.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    /// The maximum number of selections kept, the oldest ones being dropped first.
+    pub max_entries: usize,
+    /// How long selections are kept, forever if not set.
+    pub max_age: Option<Duration>,
+}
+
+impl History {
+    /// Read the history from the given directory, empty if there's none yet.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(HISTORY_FILE);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read `{}`", path.display()))
+            }
+        };
+
+        serde_json::from_str(&json).with_context(|| format!("Invalid history `{}`", path.display()))
+    }
+
+    /// How many times each emoji was selected, by code.
+    pub fn uses(&self) -> HashMap<String, usize> {
+        let mut uses = HashMap::new();
+        for entry in &self.entries {
+            *uses.entry(entry.code.clone()).or_default() += 1;
+        }
+
+        uses
+    }
+
+    /// Record the selection of the emoji in the history of the given directory, dropping the
+    /// selections that are not to be kept anymore.
+    pub fn record(dir: &Path, emoji: &Emoji, retention: Retention) -> anyhow::Result<()> {
+        let mut history = Self::load(dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        history.entries.push(Entry {
+            code: emoji.code.to_owned(),
+            emoji: emoji.emoji.to_owned(),
+            timestamp: now.as_secs(),
+            repo: git::repo_root(),
+        });
+
+        if let Some(max_age) = retention.max_age {
+            let oldest = now.saturating_sub(max_age).as_secs();
+            history.entries.retain(|entry| entry.timestamp >= oldest);
+        }
+        let excess = history.entries.len().saturating_sub(retention.max_entries);
+        history.entries.drain(..excess);
+
+        history.save(dir)
+    }
+
+    /// Forget all the selections of the history in the given directory.
+    pub fn clear(dir: &Path) -> anyhow::Result<()> {
+        let path = dir.join(HISTORY_FILE);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove `{}`", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The last search of the picker saved in the given directory, empty if there's none.
+    pub fn load_query(dir: &Path) -> anyhow::Result<String> {
+        let path = dir.join(QUERY_FILE);
+        match fs::read_to_string(&path) {
+            Ok(query) => Ok(query),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read `{}`", path.display())),
+        }
+    }
+
+    /// Save the last search of the picker in the given directory, for the next one to start with.
+    pub fn save_query(dir: &Path, query: &str) -> anyhow::Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create `{}`", dir.display()))?;
+        let path = dir.join(QUERY_FILE);
+
+        fs::write(&path, query).with_context(|| format!("Failed to write `{}`", path.display()))
+    }
+
+    fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create `{}`", dir.display()))?;
+        let path = dir.join(HISTORY_FILE);
+        let json = serde_json::to_string_pretty(self).expect("The history is valid JSON");
+
+        fs::write(&path, json).with_context(|| format!("Failed to write `{}`", path.display()))
+    }
+}
+
+const HISTORY_FILE: &str = "history.json";
+const QUERY_FILE: &str = "last-query.txt";
@@ -0,0 +1,754 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, PoisonError},
+};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emoji::{Emoji, EmojiFormat},
+    git, remap, Error,
+};
+
+/// Where the commit message comes from, as told to the `prepare-commit-msg` hook by git.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageSource {
+    Message,
+    Template,
+    Merge,
+    Squash,
+    Commit,
+}
+
+/// The version control system a hook is for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Vcs {
+    #[default]
+    Git,
+    /// Mercurial, whose hooks can't edit commit messages, so gimoji stands in for its editor.
+    Hg,
+}
+
+/// Another tool whose behavior the hook mirrors, for teams migrating from it gradually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compat {
+    /// gitmoji-cli, which asks for the scope, title and body of the message after the emoji, as set
+    /// in its `.gitmojirc.json`.
+    GitmojiCli,
+}
+
+/// The answers to gitmoji-cli's questions, which make up the commit message.
+pub struct CommitAnswers {
+    /// The emoji, as written in commit messages.
+    pub emoji: String,
+    pub scope: Option<String>,
+    pub title: String,
+    /// The body of the message.
+    pub message: Option<String>,
+}
+
+impl CommitAnswers {
+    /// The commit message, as gitmoji-cli writes it: `<emoji> [(<scope>): ]<title>`, followed by
+    /// the body after a blank line.
+    pub fn commit_message(&self, capitalize_title: bool) -> String {
+        let mut message = format!("{} ", self.emoji);
+        if let Some(scope) = &self.scope {
+            message.push_str(&format!("({scope}): "));
+        }
+        let mut title = self.title.chars();
+        if let (true, Some(first)) = (capitalize_title, title.next()) {
+            message.extend(first.to_uppercase());
+        }
+        message.push_str(title.as_str());
+        if let Some(body) = &self.message {
+            message.push_str(&format!("\n\n{body}"));
+        }
+        message.push('\n');
+
+        message
+    }
+}
+
+/// The git config file an alias is set in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AliasScope {
+    /// The user's, for all repositories.
+    #[default]
+    Global,
+    /// The one of the current repository.
+    Local,
+}
+
+impl AliasScope {
+    /// The option of `git config` selecting the file.
+    pub fn option(self) -> &'static str {
+        match self {
+            Self::Global => "--global",
+            Self::Local => "--local",
+        }
+    }
+}
+
+/// Where the hook puts the emoji in the first line of commit messages.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Position {
+    #[default]
+    Start,
+    End,
+}
+
+/// What the hook does with commit messages that already have an emoji.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Existing {
+    /// Leave them alone, except for migrating retired codes if asked to.
+    #[default]
+    Skip,
+    /// Prompt for another emoji to replace it with.
+    Replace,
+}
+
+/// How much of the commit message the picker shows while the hook prompts for an emoji.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShowMessage {
+    None,
+    /// Its first line.
+    #[default]
+    Subject,
+    /// All of it, without git's comments, cut short if it's long.
+    Full,
+}
+
+/// Whether we're running in a continuous integration service, as most of them advertise.
+pub fn is_ci() -> bool {
+    std::env::var("CI").is_ok_and(|ci| !ci.is_empty() && ci != "false" && ci != "0")
+}
+
+/// How the hook edits commit messages.
+pub struct HookOptions {
+    /// Replace a conventional commit type prefix (e.g. `feat: `) with the emoji of that code rather
+    /// than prompting for one.
+    pub convert_types: bool,
+    /// Replace the retired gitmoji codes of messages that already have an emoji.
+    pub migrate_codes: bool,
+    /// How the emoji is written.
+    pub format: EmojiFormat,
+    pub position: Position,
+    /// Replace the emoji the message already has, if any, instead of leaving the message alone.
+    pub replace_existing: bool,
+    /// Whether to prompt for an emoji when there's none to convert a commit type to.
+    pub prompt: bool,
+}
+
+/// Add an emoji to the first line of the commit message, unless it already has one.
+pub fn add_emoji(
+    path: &Path,
+    emojis: &[&'static Emoji],
+    options: HookOptions,
+    get_emoji: impl FnOnce() -> anyhow::Result<Option<&'static Emoji>>,
+) -> anyhow::Result<()> {
+    let file = File::open(path).context("Failed to open commit msg file")?;
+    let mut reader = BufReader::new(file);
+    let mut first_line = String::new();
+    reader
+        .read_line(&mut first_line)
+        .context("Failed to read first line in commit msg file")?;
+    let Some(emojified) = emojify(first_line.clone(), emojis, &options, get_emoji)? else {
+        return Ok(());
+    };
+
+    // Messages can be huge, e.g. listing the files of a merge, so the rest is streamed rather than
+    // read into memory.
+    replace_file(path, &emojified, reader)
+        .with_context(|| format!("Failed to write `{}`", path.display()))?;
+    record_insertion(path, &first_line, &emojified);
+
+    Ok(())
+}
+
+/// The line, e.g. the first one of a commit message, with an emoji added, unless it already has
+/// one, in which case it's only changed if its code is to be migrated. `None` if it's left as is.
+pub fn emojify(
+    mut content: String,
+    emojis: &[&'static Emoji],
+    options: &HookOptions,
+    get_emoji: impl FnOnce() -> anyhow::Result<Option<&'static Emoji>>,
+) -> anyhow::Result<Option<String>> {
+    if options.replace_existing {
+        // Retired codes are replaced along with the current ones.
+        if let Some(migrated) = remap::migrate(&content) {
+            content = migrated;
+        }
+        if let Some(existing) = find_emoji(&content, emojis) {
+            content = remove_emoji(&content, existing);
+        }
+    }
+
+    let has_emoji =
+        find_emoji(&content, emojis).is_some() || remap::contains_retired_code(&content);
+    if has_emoji {
+        // The commit shortlog already contains an emoji, whose code may need migrating.
+        return Ok(options
+            .migrate_codes
+            .then(|| remap::migrate(&content))
+            .flatten());
+    }
+
+    let converted = options
+        .convert_types
+        .then(|| convert_commit_type(&content, emojis))
+        .flatten();
+    let (emoji, content) = match converted {
+        Some(converted) => converted,
+        None => {
+            let emoji = if options.prompt { get_emoji()? } else { None };
+            match emoji {
+                Some(emoji) => (emoji, content),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    Ok(Some(insert_emoji(
+        &content,
+        &options.format.format(emoji),
+        options.position,
+    )))
+}
+
+/// The part of the commit message the picker shows, without git's comments. `None` if there's
+/// nothing to show, e.g. when committing without `-m`.
+pub fn shown_message(message: &str, show: ShowMessage) -> Option<String> {
+    let mut lines = message
+        .lines()
+        // What follows is the diff of `git commit -v`.
+        .take_while(|line| *line != SCISSORS_LINE)
+        .filter(|line| !line.starts_with('#'));
+    let shown = match show {
+        ShowMessage::None => return None,
+        ShowMessage::Subject => lines.next().unwrap_or_default().to_owned(),
+        ShowMessage::Full => lines.collect::<Vec<_>>().join("\n"),
+    };
+    let shown = shown.trim();
+
+    (!shown.is_empty()).then(|| shown.to_owned())
+}
+
+/// Replace the first line of the commit message with the one gitmoji-cli would write from the
+/// answers, unless it already has an emoji.
+///
+/// The answers are asked for with the first line, which is the title given with `git commit -m`,
+/// if any. gitmoji-cli's defaults are up to the caller.
+pub fn write_gitmoji_cli_message(
+    path: &Path,
+    emojis: &[&'static Emoji],
+    capitalize_title: bool,
+    ask: impl FnOnce(&str) -> anyhow::Result<Option<CommitAnswers>>,
+) -> anyhow::Result<()> {
+    let file = File::open(path).context("Failed to open commit msg file")?;
+    let mut reader = BufReader::new(file);
+    let mut first_line = String::new();
+    reader
+        .read_line(&mut first_line)
+        .context("Failed to read first line in commit msg file")?;
+    if find_emoji(&first_line, emojis).is_some() || remap::contains_retired_code(&first_line) {
+        return Ok(());
+    }
+
+    let Some(answers) = ask(first_line.trim())? else {
+        return Ok(());
+    };
+    let message = answers.commit_message(capitalize_title);
+    replace_file(path, &message, reader)
+        .with_context(|| format!("Failed to write `{}`", path.display()))?;
+    record_insertion(path, &first_line, &message);
+
+    Ok(())
+}
+
+/// What the hook last changed in a commit message, as recorded in the git directory for [`undo`].
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Insertion {
+    /// The absolute path of the commit message file.
+    pub file: PathBuf,
+    /// The first line of the message before the emoji was added.
+    pub before: String,
+    /// The first line of the message once the emoji was added.
+    pub after: String,
+}
+
+impl Insertion {
+    /// The line without the insertion, if it's still there: the line as it was before if it's
+    /// unchanged since, or else without what was added at its start or end.
+    pub fn undo_line(&self, line: &str) -> Option<String> {
+        if line == self.after {
+            return Some(self.before.clone());
+        }
+        let prefix = self.after.strip_suffix(&self.before);
+        let suffix = self.after.strip_prefix(&self.before);
+        let undone = match (prefix, suffix) {
+            (Some(prefix), _) if !prefix.is_empty() => line.strip_prefix(prefix),
+            (_, Some(suffix)) if !suffix.is_empty() => line.strip_suffix(suffix),
+            // Rewritten rather than added to, e.g. with the commit type converted.
+            _ => None,
+        };
+
+        undone.map(str::to_owned)
+    }
+}
+
+/// Where [`undo`] took the last insertion out of.
+#[derive(Debug)]
+pub enum Undone {
+    /// The commit message file, for a commit that's yet to be made.
+    File(PathBuf),
+    /// The message of the `HEAD` commit, which was amended.
+    Head,
+}
+
+/// Take the emoji the hook last added out of the commit message, or out of the `HEAD` commit if
+/// it's been made since, which is then amended.
+///
+/// Fails with [`Error::NotAGitRepo`] outside of a repository, and if the hook hasn't added an
+/// emoji since the last undo, or the emoji is gone from the message.
+pub fn undo() -> anyhow::Result<Undone> {
+    let git_dir = git::git_dir().ok_or(Error::NotAGitRepo)?;
+    let record = git_dir.join(INSERTION_FILE);
+    let insertion: Insertion = match fs::read_to_string(&record) {
+        Ok(json) => serde_json::from_str(&json)
+            .with_context(|| format!("Invalid record `{}`", record.display()))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            bail!("The hook hasn't added an emoji to undo")
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read `{}`", record.display())),
+    };
+
+    let file = File::open(&insertion.file).map(BufReader::new);
+    let mut first_line = String::new();
+    let (mut reader, line) = match file {
+        Ok(mut reader) => {
+            reader
+                .read_line(&mut first_line)
+                .with_context(|| format!("Failed to read `{}`", insertion.file.display()))?;
+            (
+                Some(reader),
+                Some(first_line.trim_end_matches(['\r', '\n'])),
+            )
+        }
+        // Git may have cleaned it up.
+        Err(_) => (None, None),
+    };
+    let head = git::head_message();
+    let subject = head.as_deref().and_then(|message| message.lines().next());
+    // Git leaves the message file behind with the message it committed.
+    let committed =
+        subject.is_some_and(|subject| subject == insertion.after || Some(subject) == line);
+
+    let undone = if let Some(message) = head.filter(|_| committed) {
+        let (subject, body) = message.split_once('\n').unwrap_or((&message, ""));
+        let Some(subject) = insertion.undo_line(subject) else {
+            bail!("The `HEAD` commit no longer has the emoji the hook added");
+        };
+        git::amend_message(&format!("{subject}\n{body}\n"))?;
+        Undone::Head
+    } else {
+        let (Some(reader), Some(line)) = (reader.take(), line) else {
+            bail!("`{}` is gone", insertion.file.display());
+        };
+        let Some(undone) = insertion.undo_line(line) else {
+            bail!(
+                "`{}` no longer has the emoji the hook added",
+                insertion.file.display()
+            );
+        };
+        let ending = &first_line[line.len()..];
+        replace_file(&insertion.file, &format!("{undone}{ending}"), reader)
+            .with_context(|| format!("Failed to write `{}`", insertion.file.display()))?;
+        Undone::File(insertion.file)
+    };
+    // Undone once and for all.
+    let _ = fs::remove_file(&record);
+
+    Ok(undone)
+}
+
+/// Record the change of the first line of the message in the file, for [`undo`] to revert it.
+///
+/// Not worth failing the commit over, so failures are only warned about. Nothing is recorded
+/// outside of git repositories, e.g. with Mercurial.
+fn record_insertion(path: &Path, before: &str, after: &str) {
+    let Some(git_dir) = git::git_dir() else {
+        return;
+    };
+    let insertion = Insertion {
+        file: path.canonicalize().unwrap_or_else(|_| path.to_owned()),
+        before: before.trim_end_matches(['\r', '\n']).to_owned(),
+        after: after.lines().next().unwrap_or_default().to_owned(),
+    };
+    let json = serde_json::to_string_pretty(&insertion).expect("The insertion is valid JSON");
+    let record = git_dir.join(INSERTION_FILE);
+    if let Err(e) = fs::write(&record, json) {
+        eprintln!("WARNING: Failed to write `{}`: {e}", record.display());
+    }
+}
+
+/// Replace the first line of the commit message file with the given one.
+pub fn replace_first_line(path: &Path, line: &str) -> anyhow::Result<()> {
+    let file = File::open(path).context("Failed to open commit msg file")?;
+    let mut reader = BufReader::new(file);
+    let mut first_line = String::new();
+    reader
+        .read_line(&mut first_line)
+        .context("Failed to read first line in commit msg file")?;
+    let ending = &first_line[first_line.trim_end_matches(['\r', '\n']).len()..];
+
+    replace_file(path, &format!("{line}{ending}"), reader)
+        .with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+/// Replace the file with the first line followed by the rest, through a temporary file in the
+/// same directory, so that a failure halfway can't leave it half-written.
+fn replace_file(path: &Path, first_line: &str, mut rest: impl Read) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Git only writes one message at a time in a repository, and WASI has no process IDs.
+    let temp = path.with_file_name(format!(".{file_name}.tmp"));
+    let mut write = || {
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        writer.write_all(first_line.as_bytes())?;
+        io::copy(&mut rest, &mut writer)?;
+        let file = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+
+        fs::rename(&temp, path)
+    };
+
+    write().inspect_err(|_| {
+        // Not worth keeping around.
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// The emoji or code of one of the emojis that's in the line, if any.
+pub fn find_emoji(line: &str, emojis: &[&'static Emoji]) -> Option<&'static str> {
+    if line.is_empty() {
+        return None;
+    }
+
+    // Embedded emojis are looked up from what could be one in the line, rather than looking for
+    // each of them in it.
+    for (start, c) in line.char_indices() {
+        let rest = &line[start..];
+        let found = match c {
+            ':' => rest[1..]
+                .find(':')
+                .and_then(|end| Emoji::by_code(&rest[..end + 2]))
+                .map(|emoji| emoji.code),
+            _ => Emoji::by_prefix(rest, emojis).map(|emoji| emoji.emoji),
+        };
+        let in_set = found.and_then(|text| {
+            emojis
+                .iter()
+                .find_map(|emoji| [emoji.emoji, emoji.code].into_iter().find(|t| *t == text))
+        });
+        if in_set.is_some() {
+            return in_set;
+        }
+    }
+
+    // The others are all looked for at once, the leftmost one being found, with an automaton
+    // built once for all the lines the same emojis are looked for in.
+    let texts: Vec<_> = emojis
+        .iter()
+        .filter(|emoji| !emoji.is_embedded())
+        .flat_map(|emoji| [emoji.emoji, emoji.code])
+        .collect();
+    let mut automaton = AUTOMATON.lock().unwrap_or_else(PoisonError::into_inner);
+    if automaton
+        .as_ref()
+        .is_none_or(|(cached, _)| *cached != texts)
+    {
+        let built = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostFirst)
+            .build(&texts)
+            .expect("The emojis fit in an automaton");
+        *automaton = Some((texts, built));
+    }
+    let (texts, automaton) = automaton.as_ref().expect("The automaton was just built");
+
+    automaton
+        .find(line)
+        .map(|found| texts[found.pattern().as_usize()])
+}
+
+/// The emojis that aren't embedded last looked for by [`find_emoji`], with their automaton.
+static AUTOMATON: Mutex<Option<(Vec<&'static str>, AhoCorasick)>> = Mutex::new(None);
+
+/// The line without the first occurrence of the emoji, along with the space separating it from the
+/// rest of the line.
+pub fn remove_emoji(line: &str, emoji: &str) -> String {
+    [format!("{emoji} "), format!(" {emoji}"), emoji.to_owned()]
+        .into_iter()
+        .find(|text| line.contains(text.as_str()))
+        .map(|text| line.replacen(&text, "", 1))
+        .unwrap_or_else(|| line.to_owned())
+}
+
+/// Insert the emoji in the line, which may end with a line break.
+pub fn insert_emoji(line: &str, emoji: &str, position: Position) -> String {
+    match position {
+        Position::Start => format!("{emoji} {line}"),
+        Position::End => {
+            let text = line.trim_end_matches(['\r', '\n']);
+            let line_break = &line[text.len()..];
+            if text.is_empty() {
+                format!("{emoji}{line_break}")
+            } else {
+                format!("{text} {emoji}{line_break}")
+            }
+        }
+    }
+}
+
+/// Split a conventional commit `<type>[(<scope>)]: <description>` line into the emoji with the type
+/// as code and the rest of the line, with the scope (if any) kept as a `<scope>: ` prefix.
+///
+/// Breaking changes (`<type>!: `) are left alone, as their marker would be lost otherwise.
+pub fn convert_commit_type(
+    line: &str,
+    emojis: &[&'static Emoji],
+) -> Option<(&'static Emoji, String)> {
+    let (prefix, description) = line.split_once(": ")?;
+    let (commit_type, scope) = match prefix.split_once('(') {
+        Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    let code = format!(":{}:", commit_type.to_ascii_lowercase());
+    let emoji = emojis.iter().find(|emoji| emoji.code == code)?;
+
+    let rest = match scope {
+        Some(scope) => format!("{scope}: {description}"),
+        None => description.to_owned(),
+    };
+
+    Some((emoji, rest))
+}
+
+/// Install gimoji as the commit message hook of the repository in the current directory, unless
+/// there's already one and it's not to be overwritten. With `daemon`, the hook has
+/// `gimoji daemon` show the picker if it's running.
+///
+/// Fails with [`Error::NotAGitRepo`] or [`Error::NotAMercurialRepo`] outside of a repository, and
+/// [`Error::HookAlreadyExists`] if there's already a hook.
+pub fn install(force: bool, vcs: Vcs, compat: Option<Compat>, daemon: bool) -> anyhow::Result<()> {
+    let mut args = compat
+        .and_then(|compat| compat.to_possible_value())
+        .map(|compat| format!(" --compat {}", compat.get_name()))
+        .unwrap_or_default();
+    if daemon {
+        args.push_str(" --daemon");
+    }
+    match vcs {
+        Vcs::Git => install_git(force, &args),
+        Vcs::Hg => install_hg(force, &args),
+    }
+}
+
+/// Install gimoji as the `prepare-commit-msg` hook, in the hooks directory git actually uses.
+fn install_git(force: bool, args: &str) -> anyhow::Result<()> {
+    let hooks_dir = git::hooks_dir().ok_or(Error::NotAGitRepo)?;
+    fs::create_dir_all(&hooks_dir).context("Failed to create hooks dir")?;
+    let file_path = hooks_dir.join(PRE_COMMIT_MSG_HOOK);
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o744);
+    }
+
+    let file = match options.open(&file_path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(Error::HookAlreadyExists(file_path).into())
+        }
+        Err(e) => return Err(anyhow::anyhow!(e)).context("Failed to create hook file"),
+    };
+
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(HOOK_HEADER.as_bytes())
+        .context("Failed to write hook header")?;
+    writer
+        .write_all(format!("{HOOK_CMD}{args} $1 $2").as_bytes())
+        .context("Failed to write hook command")?;
+    writer.flush().context("Failed to flush hook buffer")?;
+
+    Ok(())
+}
+
+/// Install gimoji as the editor of the repository, in its `.hg/hgrc`, for it to add the emoji
+/// before opening the actual editor with [`run_editor`].
+///
+/// Mercurial has no hook able to edit commit messages, `precommit` and `pretxncommit` can only
+/// reject them.
+fn install_hg(force: bool, args: &str) -> anyhow::Result<()> {
+    let root = hg_root().ok_or(Error::NotAMercurialRepo)?;
+    let path = root.join(HG_CONFIG);
+    let config = match fs::read_to_string(&path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read `{}`", path.display())),
+    };
+    let editor = format!("editor = {HG_EDITOR_CMD}{args}");
+    if force && config.lines().any(|line| line.trim() == editor) {
+        return Ok(());
+    }
+    // Whichever editor is set, gimoji included, it would be overridden.
+    if !force && has_hg_editor(&config) {
+        return Err(Error::HookAlreadyExists(path).into());
+    }
+
+    let mut section = String::new();
+    if !config.is_empty() && !config.ends_with('\n') {
+        section.push('\n');
+    }
+    section.push_str(&format!("{HG_HEADER}{editor}\n"));
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(section.as_bytes()))
+        .with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+/// The root of the Mercurial working tree the current directory is in, if any.
+fn hg_root() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .find(|dir| dir.join(".hg").is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Whether the Mercurial config sets `ui.editor`.
+fn has_hg_editor(config: &str) -> bool {
+    let mut in_ui = false;
+    config.lines().any(|line| {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[') {
+            in_ui = section.strip_suffix(']').map(str::trim) == Some("ui");
+            return false;
+        }
+
+        in_ui
+            && line
+                .split_once('=')
+                .is_some_and(|(key, _)| key.trim() == "editor")
+    })
+}
+
+/// Open the commit message at the given path in the editor of the user, as Mercurial would have
+/// without gimoji standing in for it.
+pub fn run_editor(path: &Path) -> anyhow::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_owned());
+
+    // Through the shell like Mercurial does, as editors are often set with arguments.
+    #[cfg(not(windows))]
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status();
+    #[cfg(windows)]
+    let status = Command::new("cmd")
+        .arg("/C")
+        .arg(format!("{editor} \"{}\"", path.display()))
+        .status();
+
+    let status = status.with_context(|| format!("Failed to run `{editor}`"))?;
+    if !status.success() {
+        bail!("`{editor}` exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// Set `alias.<name>` in the git config to [`ALIAS_COMMAND`], for `git <name> <title>` to commit
+/// with an emoji added to the title, unless it's set to something else and not to be overwritten.
+pub fn install_alias(name: &str, scope: AliasScope, force: bool) -> anyhow::Result<()> {
+    if scope == AliasScope::Local && git::repo_root().is_none() {
+        return Err(Error::NotAGitRepo.into());
+    }
+    let key = format!("alias.{name}");
+    match git::config_get_in(scope.option(), &key) {
+        Some(command) if command == ALIAS_COMMAND => return Ok(()),
+        Some(command) if !force => {
+            bail!("`{key}` is already set to `{command}`, use -f to overwrite it")
+        }
+        _ => {}
+    }
+
+    git::config_set_in(scope.option(), &key, ALIAS_COMMAND)
+}
+
+/// The command of the alias set by [`install_alias`], committing with the emoji `gimoji print`
+/// prints followed by the title, and nothing if no emoji is selected.
+pub const ALIAS_COMMAND: &str =
+    r#"!f() { emoji=$(gimoji print) && git commit -m "$emoji $*"; }; f"#;
+
+const INSERTION_FILE: &str = "gimoji-insertion.json";
+const PRE_COMMIT_MSG_HOOK: &str = "prepare-commit-msg";
+const HOOK_HEADER: &str = "#!/usr/bin/env bash\n# gimoji as a commit hook\n";
+const HOOK_CMD: &str = "gimoji hook";
+const SCISSORS_LINE: &str = "# ------------------------ >8 ------------------------";
+const HG_CONFIG: &str = ".hg/hgrc";
+const HG_HEADER: &str = "[ui]\n# gimoji as a commit hook, opening $VISUAL or $EDITOR afterwards\n";
+const HG_EDITOR_CMD: &str = "gimoji hook --vcs hg";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emoji::{DEVMOJI, GITMOJI};
+
+    #[test]
+    fn find_emoji_in_the_set_only() {
+        let devmoji: Vec<_> = DEVMOJI.emojis().iter().collect();
+        let gitmoji: Vec<_> = GITMOJI.emojis().iter().collect();
+
+        // gitmoji's `⚡️` has a variation selector that devmoji's `⚡` doesn't have.
+        assert_eq!(find_emoji("⚡ Speed up", &devmoji), Some("⚡"));
+        assert_eq!(find_emoji("⚡️ Speed up", &devmoji), Some("⚡"));
+        assert_eq!(find_emoji("⚡️ Speed up", &gitmoji), Some("⚡️"));
+        assert_eq!(find_emoji("Speed up :zap:", &gitmoji), Some(":zap:"));
+        assert_eq!(find_emoji("Speed up :zap:", &devmoji), None);
+        assert_eq!(find_emoji("Speed up", &gitmoji), None);
+    }
+}
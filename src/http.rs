@@ -0,0 +1,103 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use ureq::{Agent, AgentBuilder};
+
+/// The outcome of [`download`].
+pub enum Download {
+    /// The resource still has the given ETag.
+    NotModified,
+    Downloaded {
+        body: String,
+        etag: Option<String>,
+    },
+}
+
+/// Download the URL, unless it still has the given ETag.
+///
+/// The body goes through `<path>.part` first, which is kept if the download is interrupted so that
+/// the next one resumes from it, if the resource didn't change since as told by its ETag. The proxy
+/// set with `HTTPS_PROXY` or the like is used, if any.
+pub fn download(url: &str, if_none_match: Option<&str>, path: &Path) -> anyhow::Result<Download> {
+    let part = with_suffix(path, ".part");
+    let part_etag = with_suffix(path, ".part.etag");
+    let mut resume = fs::read_to_string(&part_etag)
+        .ok()
+        .zip(fs::metadata(&part).ok())
+        .map(|(etag, metadata)| (etag, metadata.len()));
+
+    let agent = agent();
+    let response = loop {
+        let mut request = agent.get(url);
+        if let Some(etag) = if_none_match {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some((etag, len)) = &resume {
+            request = request
+                .set("Range", &format!("bytes={len}-"))
+                .set("If-Range", etag);
+        }
+        match request.call() {
+            // The partial body is complete or obsolete, either way it's to be downloaded again.
+            Err(ureq::Error::Status(416, _)) if resume.is_some() => resume = None,
+            response => break response.with_context(|| format!("Failed to fetch `{url}`"))?,
+        }
+    };
+    if response.status() == 304 {
+        return Ok(Download::NotModified);
+    }
+
+    let etag = response.header("ETag").map(str::to_owned);
+    let mut file = if response.status() == 206 {
+        OpenOptions::new().append(true).open(&part)
+    } else {
+        // Without an ETag, the next download can't tell whether it may resume from this one.
+        match &etag {
+            Some(etag) => fs::write(&part_etag, etag),
+            None => fs::remove_file(&part_etag).or_else(ignore_not_found),
+        }
+        .and_then(|()| File::create(&part))
+    }
+    .with_context(|| format!("Failed to write `{}`", part.display()))?;
+    io::copy(&mut response.into_reader(), &mut file).with_context(|| {
+        format!("Failed to download `{url}`, run the command again to resume the download")
+    })?;
+
+    let body = fs::read_to_string(&part)
+        .with_context(|| format!("Failed to read `{}`", part.display()))?;
+    // Not worth failing over, the download is complete.
+    let _ = fs::remove_file(&part).and_then(|()| fs::remove_file(&part_etag));
+
+    Ok(Download::Downloaded { body, etag })
+}
+
+fn agent() -> Agent {
+    AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .try_proxy_from_env(true)
+        .build()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+
+    path.into()
+}
+
+fn ignore_not_found(e: io::Error) -> io::Result<()> {
+    match e.kind() {
+        io::ErrorKind::NotFound => Ok(()),
+        _ => Err(e),
+    }
+}
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the server may stay silent, rather than how long the whole download may take.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
@@ -0,0 +1,288 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An action of the picker, which can be bound to keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Pick the selected emoji.
+    Select,
+    /// Clear the search text, or exit if it's empty already.
+    Cancel,
+    Exit,
+    Up,
+    Down,
+    /// Delete the last character of the search text.
+    DeleteChar,
+    CycleSet,
+    CycleMatchMode,
+}
+
+/// The keys bound to each [`Action`].
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Chords)>,
+}
+
+impl Keymap {
+    /// The default bindings, with the ones given for some actions instead.
+    pub fn new(bindings: impl IntoIterator<Item = (Action, Option<Chords>)>) -> Self {
+        let mut keymap = Self::default();
+        for (action, chords) in bindings {
+            if let Some(chords) = chords {
+                keymap.bind(action, chords);
+            }
+        }
+
+        keymap
+    }
+
+    /// The keys bound to the action, if any.
+    pub fn chords(&self, action: Action) -> Option<&Chords> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, chords)| chords)
+    }
+
+    /// The action bound to the key pressed with the modifiers, if any.
+    pub fn action(&self, key: Key, modifiers: Modifiers) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, chords)| chords.0.iter().any(|chord| chord.matches(key, modifiers)))
+            .map(|(action, _)| *action)
+    }
+
+    fn bind(&mut self, action: Action, chords: Chords) {
+        match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, existing)) => *existing = chords,
+            None => self.bindings.push((action, chords)),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = [
+            (Action::Select, "enter"),
+            (Action::Cancel, "esc"),
+            (Action::Exit, "ctrl+c"),
+            (Action::Up, "up backtab"),
+            (Action::Down, "down tab"),
+            (Action::DeleteChar, "backspace"),
+            (Action::CycleSet, "ctrl+s"),
+            (Action::CycleMatchMode, "ctrl+r"),
+        ];
+
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(action, chords)| {
+                    let chords = chords
+                        .split(' ')
+                        .map(|chord| chord.parse().expect("Default key bindings are valid"))
+                        .collect();
+
+                    (action, Chords(chords))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The keys bound to an action, given as a single chord or a list of them in the config.
+#[derive(Debug, Clone)]
+pub struct Chords(Vec<KeyChord>);
+
+impl<'de> Deserialize<'de> for Chords {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let chords = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(chord) => vec![chord],
+            OneOrMany::Many(chords) => chords,
+        };
+        chords
+            .iter()
+            .map(|chord| chord.parse().map_err(serde::de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Serialize for Chords {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ToString::to_string))
+    }
+}
+
+/// A key of the keyboard, independently of how the terminal reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    /// A function key, from 1 to 24.
+    F(u8),
+    Enter,
+    Esc,
+    Tab,
+    BackTab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+/// The modifier keys held down along with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+/// A key along with its modifiers, e.g. `ctrl+s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    key: Key,
+    modifiers: Modifiers,
+}
+
+impl KeyChord {
+    fn matches(&self, key: Key, mut modifiers: Modifiers) -> bool {
+        // Shift is part of the character (or of back tab) already.
+        if matches!(key, Key::Char(_) | Key::BackTab) {
+            modifiers.shift = false;
+        }
+
+        key == self.key && modifiers == self.modifiers
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = InvalidChord;
+
+    /// Parse a chord of `+`-separated modifiers (`ctrl`, `alt` or `shift`) followed by a key, which
+    /// is either a single character or the name of a special key, e.g. `ctrl+alt+x` or `f2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| InvalidChord {
+            chord: s.to_owned(),
+            reason,
+        };
+        // `+` itself can be a key, e.g. `ctrl++`.
+        let (modifiers, key) = match s.rsplit_once('+') {
+            Some((modifiers, "")) => (modifiers.strip_suffix('+').unwrap_or(modifiers), "+"),
+            Some((modifiers, key)) => (modifiers, key),
+            None => ("", s),
+        };
+
+        let mut chord = KeyChord {
+            key: parse_key(key).ok_or_else(|| invalid(format!("unknown key `{key}`")))?,
+            modifiers: Modifiers::default(),
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            let held = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut chord.modifiers.ctrl,
+                "alt" => &mut chord.modifiers.alt,
+                "shift" => &mut chord.modifiers.shift,
+                _ => return Err(invalid(format!("unknown modifier `{modifier}`"))),
+            };
+            *held = true;
+        }
+
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    /// Write the chord the way it's parsed, e.g. `ctrl+alt+x`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "ctrl"),
+            (self.modifiers.alt, "alt"),
+            (self.modifiers.shift, "shift"),
+        ];
+        for (held, name) in modifiers {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        match self.key {
+            Key::Char(' ') => f.write_str("space"),
+            Key::Char(c) => write!(f, "{c}"),
+            Key::F(n) => write!(f, "f{n}"),
+            key => {
+                let name = SPECIAL_KEYS
+                    .iter()
+                    .find(|(_, k)| *k == key)
+                    .map_or("?", |(name, _)| name);
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// The key of a single character or special key name, e.g. `x` or `pageup`.
+fn parse_key(key: &str) -> Option<Key> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+
+    let key = key.to_ascii_lowercase();
+    let name = match key.as_str() {
+        "space" => return Some(Key::Char(' ')),
+        "return" => "enter",
+        "escape" => "esc",
+        "del" => "delete",
+        name => name,
+    };
+    if let Some((_, key)) = SPECIAL_KEYS.iter().find(|(n, _)| *n == name) {
+        return Some(*key);
+    }
+
+    let n = name.strip_prefix('f')?.parse().ok()?;
+    (1..=24).contains(&n).then_some(Key::F(n))
+}
+
+/// The names of the special keys, besides `space` and the function keys.
+const SPECIAL_KEYS: [(&str, Key); 14] = [
+    ("enter", Key::Enter),
+    ("esc", Key::Esc),
+    ("tab", Key::Tab),
+    ("backtab", Key::BackTab),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+];
+
+/// A key chord that couldn't be parsed.
+#[derive(Debug)]
+pub struct InvalidChord {
+    chord: String,
+    reason: String,
+}
+
+impl fmt::Display for InvalidChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key `{}`: {}", self.chord, self.reason)
+    }
+}
@@ -0,0 +1,76 @@
+//! Select emojis for git commit messages.
+//!
+//! This is the logic behind the `gimoji` command line tool, for other tools to reuse: the emoji
+//! [database], searching it with [query] and [matcher], the interactive picker of [terminal],
+//! adding emojis to commit message files as well as installing the commit hook doing so with
+//! [hook], checking them with [lint], recommending version bumps from the emojis of commits with
+//! [bump], and suggesting emojis for commits from their branch and changes with [suggest]. The
+//! settings of all of these are read from the config files, git config and environment variables by
+//! [settings].
+
+pub mod bump;
+pub mod cache;
+pub mod clipboard;
+pub mod colors;
+#[cfg(feature = "tui")]
+mod commit_list;
+pub mod config;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod daemon;
+pub mod database;
+pub mod emoji;
+mod error;
+mod git;
+pub mod gitmojirc;
+pub mod history;
+pub mod hook;
+#[cfg(feature = "network")]
+mod http;
+pub mod keys;
+pub mod lint;
+mod locale;
+pub mod matcher;
+#[cfg(feature = "tui")]
+mod message_pane;
+mod normalize;
+mod paths;
+pub mod picker;
+pub mod query;
+pub mod rebase;
+mod remap;
+mod schema;
+#[cfg(feature = "tui")]
+mod search_entry;
+#[cfg(feature = "tui")]
+mod selection_view;
+pub mod settings;
+pub mod suggest;
+pub mod synonyms;
+pub mod terminal;
+#[cfg(feature = "tui")]
+mod variant_picker;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+pub use error::Error;
+
+/// How search results are output.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// One emoji per line, followed by its code and description.
+    #[default]
+    Text,
+    /// A JSON array with all the details of each emoji.
+    Json,
+}
+
+/// The colors of the picker, to suit the terminal's background.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    Light,
+    #[default]
+    Dark,
+}
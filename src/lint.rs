@@ -0,0 +1,148 @@
+use serde::Serialize;
+
+use crate::{
+    emoji::Emoji,
+    hook::{self, Position},
+};
+
+/// The rules commit messages are checked against, see [`LintConfig`](crate::config::LintConfig).
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Whether the subject must have an emoji.
+    pub require_emoji: bool,
+    /// Where the emoji must be in the subject, anywhere if not set.
+    pub position: Option<Position>,
+    /// The codes of the only emojis allowed, all the ones of the set if not set.
+    pub allowed: Option<Vec<String>>,
+    /// The maximum number of emojis in the subject, if limited.
+    pub max_emojis: Option<usize>,
+    /// Whether the emoji must be the one of the conventional commit type the subject starts with,
+    /// if any, e.g. `:feat:` for `feat: `.
+    pub match_commit_type: bool,
+}
+
+/// One of the [`Rules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    RequireEmoji,
+    Position,
+    Allowed,
+    MaxEmojis,
+    MatchCommitType,
+}
+
+impl Rule {
+    /// The name of the rule, which is also its key in the `[lint]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RequireEmoji => "require-emoji",
+            Self::Position => "position",
+            Self::Allowed => "allowed",
+            Self::MaxEmojis => "max-emojis",
+            Self::MatchCommitType => "match-commit-type",
+        }
+    }
+}
+
+/// A rule the commit message breaks, with what's wrong.
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub rule: Rule,
+    pub message: String,
+}
+
+/// Check the subject of the commit message, i.e. its first line that's not a comment, against the
+/// rules, with the emojis of the given set.
+///
+/// Messages of commits meant to be squashed, starting with `fixup!`, `squash!` or `amend!`, are
+/// left alone.
+pub fn lint(message: &str, emojis: &[&'static Emoji], rules: &Rules) -> Vec<Violation> {
+    let subject = message
+        .lines()
+        .find(|line| !line.starts_with('#'))
+        .unwrap_or_default()
+        .trim();
+    if SQUASHED_PREFIXES
+        .iter()
+        .any(|prefix| subject.starts_with(prefix))
+    {
+        return Vec::new();
+    }
+
+    let found = find_emojis(subject, emojis);
+    let mut violations = Vec::new();
+    let mut violation = |rule, message| violations.push(Violation { rule, message });
+    let Some(&(text, emoji)) = found.first() else {
+        if rules.require_emoji {
+            violation(Rule::RequireEmoji, "The subject has no emoji".to_owned());
+        }
+        return violations;
+    };
+
+    match rules.position {
+        Some(Position::Start) if !subject.starts_with(text) => violation(
+            Rule::Position,
+            format!("The subject must start with its emoji `{text}`"),
+        ),
+        Some(Position::End) if !subject.ends_with(text) => violation(
+            Rule::Position,
+            format!("The subject must end with its emoji `{text}`"),
+        ),
+        _ => {}
+    }
+    if let Some(allowed) = &rules.allowed {
+        for (text, emoji) in &found {
+            if !allowed.iter().any(|code| code == emoji.code) {
+                violation(
+                    Rule::Allowed,
+                    format!("`{text}` ({}) is not an allowed emoji", emoji.code),
+                );
+            }
+        }
+    }
+    if let Some(max) = rules.max_emojis.filter(|max| found.len() > *max) {
+        violation(
+            Rule::MaxEmojis,
+            format!("The subject has {} emojis, more than {max}", found.len()),
+        );
+    }
+    if rules.match_commit_type {
+        let rest = hook::remove_emoji(subject, text);
+        let expected = hook::convert_commit_type(rest.trim_start(), emojis)
+            .map(|(expected, _)| expected)
+            .filter(|expected| expected.code != emoji.code);
+        if let Some(expected) = expected {
+            violation(
+                Rule::MatchCommitType,
+                format!(
+                    "The emoji of the commit type is `{}` ({}), not `{text}`",
+                    expected.emoji, expected.code
+                ),
+            );
+        }
+    }
+
+    violations
+}
+
+/// The emojis in the line, as written and in the set, in order.
+fn find_emojis(line: &str, emojis: &[&'static Emoji]) -> Vec<(&'static str, &'static Emoji)> {
+    let mut found = Vec::new();
+    let mut rest = line.to_owned();
+    while let Some(text) = hook::find_emoji(&rest, emojis) {
+        let Some(emoji) = emojis
+            .iter()
+            .find(|emoji| emoji.emoji == text || emoji.code == text)
+        else {
+            break;
+        };
+        found.push((text, *emoji));
+        rest = rest.replacen(text, " ", 1);
+    }
+
+    found
+}
+
+/// The prefixes git gives the messages of commits to squash with `rebase --autosquash`.
+const SQUASHED_PREFIXES: [&str; 3] = ["fixup!", "squash!", "amend!"];
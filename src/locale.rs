@@ -0,0 +1,60 @@
+use std::env;
+
+/// The user's language, e.g. `fr` for `fr_FR.UTF-8`, if it's not English.
+///
+/// It's taken from the `language` setting if set, or the usual locale environment variables
+/// otherwise.
+pub fn language(setting: Option<&str>) -> Option<String> {
+    let locale = match setting {
+        Some(language) => language.to_owned(),
+        None => ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|locale| !locale.is_empty())?,
+    };
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match language.as_str() {
+        "" | "c" | "posix" | "en" => None,
+        _ => Some(language),
+    }
+}
+
+/// The translated descriptions of the emojis of a set, as `(code, description)` pairs.
+pub fn descriptions(set: &str, language: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    TRANSLATIONS
+        .iter()
+        .find(|t| t.set == set && t.language == language)
+        .map(|t| t.descriptions)
+}
+
+/// The translations of an emoji set to a language, generated by the build script.
+pub struct Translation {
+    set: &'static str,
+    language: &'static str,
+    descriptions: &'static [(&'static str, &'static str)],
+}
+
+include!(concat!(env!("OUT_DIR"), "/translations.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages() {
+        assert_eq!(language(Some("fr")).as_deref(), Some("fr"));
+        assert_eq!(language(Some("de_DE.UTF-8")).as_deref(), Some("de"));
+        assert_eq!(language(Some("ES-mx")).as_deref(), Some("es"));
+        assert_eq!(language(Some("sr@latin")).as_deref(), Some("sr"));
+        // English is the original language.
+        assert_eq!(language(Some("en_US.UTF-8")), None);
+        assert_eq!(language(Some("C")), None);
+        assert_eq!(language(Some("POSIX")), None);
+        assert_eq!(language(Some("")), None);
+    }
+}
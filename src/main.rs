@@ -1,23 +1,40 @@
-extern crate self as gimoji;
-
-mod colors;
-mod emoji;
-mod search_entry;
-mod selection_view;
-mod terminal;
-
 use anyhow::{bail, Context};
-use arboard::Clipboard;
-use clap::{command, Parser, Subcommand, ValueEnum};
-use colors::Colors;
+use clap::{Parser, Subcommand};
+#[cfg(all(unix, feature = "daemon"))]
+use gimoji::daemon;
+use gimoji::{
+    bump::{self, Recommendation},
+    cache,
+    clipboard::{self, ClipboardBackend, ClipboardSelection},
+    config::{Config, ConfigFile, SearchConfig},
+    database::{Database, EmojiSet},
+    emoji::{Emoji, EmojiFormat, Field, Order, Semver},
+    gitmojirc::GitmojiRc,
+    history::{Entry, History, Preselect},
+    hook::{
+        self, AliasScope, CommitAnswers, Compat, Existing, HookOptions, MessageSource, Undone, Vcs,
+    },
+    lint::{self, Violation},
+    matcher::MatchMode,
+    picker::{self, Picker},
+    query::{Query, SearchOptions},
+    rebase,
+    settings::{Settings, SECONDS_PER_DAY},
+    suggest::{self, Changes, Suggestion},
+    terminal::{self, Screen},
+    ColorScheme, Error, OutputFormat,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{
+    collections::HashMap,
     fmt::Debug,
-    fs::{self, OpenOptions},
-    io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    fs,
+    io::{self, IsTerminal, Write},
+    ops::Range,
     path::{Path, PathBuf},
-    process,
+    process::{self, ExitCode},
 };
-use terminal::{EventResponse, Terminal};
 
 /// Select emoji for git commit message.
 #[derive(Parser, Debug)]
@@ -25,6 +42,14 @@ use terminal::{EventResponse, Terminal};
 struct Args {
     #[command(subcommand)]
     cmd: Command,
+    /// Configuration profile to use, i.e. the `[profile.<name>]` table whose settings apply on top
+    /// of the others of each config file. Can also be set with `GIMOJI_PROFILE`.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Fail on invalid settings in the config files, git config or environment variables, rather
+    /// than ignoring them with a warning.
+    #[arg(long, global = true)]
+    strict_config: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -34,11 +59,213 @@ enum Command {
         /// Force initialize hook, use with caution
         #[arg(short, long)]
         force: bool,
+        /// Version control system of the repository. With Mercurial, gimoji is set as the editor in
+        /// `.hg/hgrc`, and opens `$VISUAL` or `$EDITOR` once the emoji is added.
+        #[arg(long, value_enum, default_value_t)]
+        vcs: Vcs,
+        /// Have the hook behave like another tool, passing it `--compat`.
+        #[arg(long, value_enum)]
+        compat: Option<Compat>,
+        /// Have the hook go through `gimoji daemon` when it's running, passing it `--daemon`.
+        #[arg(long)]
+        daemon: bool,
+    },
+    /// Set up a git alias committing with an emoji added to the given title, e.g. `git emoji "Fix
+    /// the build"`.
+    InstallAlias {
+        /// Name of the alias, e.g. `cm` for `git cm`.
+        #[arg(long, default_value = "emoji")]
+        name: String,
+        /// Git config file to set it in.
+        #[arg(long, value_enum, default_value_t)]
+        scope: AliasScope,
+        /// Overwrite an alias of the same name.
+        #[arg(short, long)]
+        force: bool,
+        /// Print the git config command making the change instead of running it.
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Select and copy an emoji to clipboard.
     Copy {
+        /// Print the emoji instead of copying it, e.g. on machines without a clipboard.
+        #[arg(long)]
+        no_clipboard: bool,
+        /// How to reach the clipboard [default: auto].
+        #[arg(long, value_enum)]
+        clipboard_backend: Option<ClipboardBackend>,
+        /// Command to copy the emoji with, run by the shell with the emoji on its standard input,
+        /// e.g. `wl-copy` or `xclip -selection clipboard`.
+        #[arg(long)]
+        clipboard_command: Option<String>,
+        /// Which clipboard to set, on systems with X11 or Wayland [default: clipboard].
+        #[arg(long, value_enum)]
+        clipboard_selection: Option<ClipboardSelection>,
+        /// Seconds after which to stop holding the clipboard in the background, on systems with X11
+        /// or Wayland [default: none].
+        #[arg(long)]
+        clipboard_timeout: Option<u64>,
+        /// Hold the clipboard in the foreground until Ctrl+C, rather than in the background, on
+        /// systems with X11 or Wayland.
+        #[arg(long)]
+        wait: bool,
+        /// Also load the emoji into a tmux paste buffer when running in tmux, to paste with
+        /// `prefix+]`.
+        #[arg(long)]
+        tmux_buffer: bool,
+        /// Also write the emoji to this file, e.g. for editor plugins to read it from. It's
+        /// replaced as a whole once written, and left alone if no emoji is selected.
+        #[arg(long)]
+        output_file: Option<PathBuf>,
+        /// For editors running gimoji in an embedded terminal: only write the emoji to the
+        /// `--output-file`, or else print it, without touching the clipboard or staying in the
+        /// background. Exits with status 130 if no emoji is selected.
+        #[arg(long, conflicts_with_all = ["wait", "tmux_buffer"])]
+        embedded: bool,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Select an emoji and print it, e.g. for a lazygit custom command to substitute it into a
+    /// commit command. Exits with status 130 if no emoji is selected.
+    Print {
+        /// Draw the picker below the cursor rather than over the whole terminal.
+        #[arg(long)]
+        no_alt_screen: bool,
+        /// Number of lines the picker takes with `--no-alt-screen` [default: 15].
+        #[arg(long, requires = "no_alt_screen", value_parser = clap::value_parser!(u16).range(1..))]
+        height: Option<u16>,
+        /// How the emoji is printed: `emoji`, `code`, or a template where `{<field>}` is replaced
+        /// with that field, e.g. `{emoji} ({name})`. Overrides `--emoji-format` [default: the
+        /// format of commit messages].
+        #[arg(long)]
+        output: Option<EmojiFormat>,
+        /// Print the emoji without a line break, for shell widgets to insert it in the command
+        /// line.
+        #[arg(long)]
+        widget: bool,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Add an emoji to a pull request title, e.g. for `gh pr create --title "$(gimoji pr-title
+    /// "$TITLE")"`, the way the hook does to commit messages. Exits with status 130 if no emoji is
+    /// selected.
+    PrTitle {
+        /// The title, read from stdin if not given.
+        #[arg()]
+        title: Vec<String>,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Print the emojis matching a search query.
+    Search {
+        /// Search query, using the same syntax as the interactive search.
+        #[arg(required_unless_present = "category")]
+        query: Vec<String>,
+        /// Only list the emojis of the category, as a `cat:<category>` term would.
+        #[arg(long)]
+        category: Option<String>,
+        /// Emoji set to search [default: unicode].
+        #[arg(long)]
+        set: Option<String>,
+        /// Format of the results [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        #[command(flatten)]
+        search: SearchArgs,
+    },
+    /// Print all the emojis of a set, in the order the picker lists them.
+    List {
+        /// Only list the emojis of the category, e.g. `deps`.
+        #[arg(long)]
+        category: Option<String>,
+        /// Emoji set to list [default: unicode].
+        #[arg(long)]
+        set: Option<String>,
+        /// Order the emojis are listed in [default: database].
+        #[arg(long, value_enum)]
+        order: Option<Order>,
+        /// Format of the list [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Recommend a version bump from the emojis of the commits since the last tag: `major`,
+    /// `minor`, `patch` or `none` on the first line, followed by the commits calling for it.
+    Bump {
+        /// Emoji set whose semver to go by [default: gitmoji].
+        #[arg(long)]
+        set: Option<String>,
+        /// Format of the recommendation [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Print the emojis suggested for the staged changes by the `[suggest]` rules, the best first
+    /// with their scores, as the hook's picker lists them first.
+    Suggest {
+        /// Branch to suggest emojis for instead of the current one.
+        #[arg(long)]
+        branch: Option<String>,
+        /// Emoji set the emojis are from [default: unicode].
+        #[arg(long)]
+        set: Option<String>,
+        /// Format of the suggestions [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Check a commit message against the `[lint]` rules of the config, e.g. in a `commit-msg` hook
+    /// or in CI. Exits with status 7 if it breaks any.
+    Lint {
+        /// The commit message file, read from stdin if not given or `-`.
+        #[arg()]
+        msg_file: Option<PathBuf>,
+        /// Emoji set the emojis are from [default: unicode].
+        #[arg(long)]
+        set: Option<String>,
+        /// Format of the violations [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Take the emoji the hook last added out of the commit message, or out of the last commit if
+    /// it's been made since, which is then amended.
+    Undo,
+    /// Pick emojis for the commits of the range that have none, listed beside the picker, then
+    /// reword them with an interactive rebase.
+    Rebase {
+        /// The commits to go through, which must end at `HEAD`, e.g. `main..` for the ones of the
+        /// current branch.
+        range: String,
+        /// Print the todo list of the rebase instead of starting it.
         #[arg(long)]
-        color_scheme: Option<ColorScheme>,
+        print: bool,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Write the new subject of the commit being reworded by `gimoji rebase`, as git's editor.
+    #[command(hide = true)]
+    Reword { msg_file: PathBuf },
+    /// Print the emojis last selected in the picker, most recent first, with when and in which
+    /// repository.
+    History {
+        #[command(subcommand)]
+        cmd: Option<HistoryCommand>,
+        /// Number of selections to print.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        /// Format of the selections [default: text].
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Fetch the latest gitmoji database, used by the gitmoji set from then on.
+    Update,
+    /// Answer requests from editor extensions on stdin, one JSON object per line, to use gimoji as
+    /// their emoji engine. See the README for the protocol.
+    Serve {
+        #[command(flatten)]
+        search: SearchArgs,
+    },
+    /// Read or change the settings of a config file.
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCommand,
     },
     /// Run as git hook
     Hook {
@@ -46,247 +273,1335 @@ enum Command {
         msg_file: PathBuf,
         #[arg()]
         msg_source: Option<MessageSource>,
+        /// Version control system running the hook. Mercurial runs it as the editor, so it then
+        /// opens `$VISUAL` or `$EDITOR`.
+        #[arg(long, value_enum, default_value_t)]
+        vcs: Vcs,
+        /// Behave like another tool, writing the same commit messages from the same questions.
+        #[arg(long, value_enum)]
+        compat: Option<Compat>,
+        /// Replace the codes retired by gitmoji with their current equivalents.
+        #[arg(long)]
+        migrate_codes: bool,
+        /// Have `gimoji daemon` show the picker if it's running, which saves loading everything.
+        /// The daemon's own settings then apply.
+        #[arg(long)]
+        daemon: bool,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Keep the emojis and settings loaded, for `hook --daemon` to show the picker instantly.
+    /// Restart it for changes of the settings or the database to apply.
+    #[cfg(all(unix, feature = "daemon"))]
+    Daemon {
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the value of a setting, e.g. `search.match-mode`. Exits with status 1 if it's not set.
+    Get {
+        key: String,
+        #[command(flatten)]
+        file: ConfigFileArgs,
+    },
+    /// Set a setting, with its value written as in TOML where quotes aren't needed.
+    Set {
+        key: String,
+        value: String,
+        #[command(flatten)]
+        file: ConfigFileArgs,
+    },
+    /// Print the effective value of every setting, given the options along with it, i.e. all the
+    /// settings except the ones without a fixed default, like the detected color scheme.
+    Show {
+        /// Prefix each setting with where it comes from: `default`, `gitmojirc`, `user:<file>`,
+        /// `git:<key>`, `repo:<file>`, `env:<variable>` or `cli`.
         #[arg(long)]
-        color_scheme: Option<ColorScheme>,
+        origin: bool,
+        #[command(flatten)]
+        picker: PickerArgs,
+    },
+    /// Update a config file written for an older version of gimoji, whose renamed settings are
+    /// otherwise only migrated in memory with a warning, keeping the original as a `.v<N>.bak`.
+    Migrate {
+        #[command(flatten)]
+        file: ConfigFileArgs,
     },
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum MessageSource {
-    Message,
-    Template,
-    Merge,
-    Squash,
-    Commit,
+#[derive(Debug, Subcommand)]
+enum HistoryCommand {
+    /// Forget all the selections.
+    Clear,
 }
 
-#[derive(ValueEnum, Debug, Clone, Copy)]
-enum ColorScheme {
-    Light,
-    Dark,
+#[derive(Debug, clap::Args)]
+struct ConfigFileArgs {
+    /// Use the repository's `.gimoji.toml` instead of your `config.toml`.
+    #[arg(long)]
+    repo: bool,
 }
 
-impl From<ColorScheme> for Colors {
-    fn from(c: ColorScheme) -> Self {
-        match c {
-            ColorScheme::Dark => Colors::DARK,
-            ColorScheme::Light => Colors::LIGHT,
+impl From<ConfigFileArgs> for ConfigFile {
+    fn from(args: ConfigFileArgs) -> Self {
+        if args.repo {
+            Self::Repo
+        } else {
+            Self::User
         }
     }
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+/// Options of the interactive emoji picker.
+#[derive(Debug, clap::Args)]
+struct PickerArgs {
+    #[arg(long)]
+    color_scheme: Option<ColorScheme>,
+    /// Don't probe the terminal for its background color when no color scheme is set, and use the
+    /// `default-color-scheme` setting [default: dark] instead.
+    #[arg(long)]
+    no_detect_color_scheme: bool,
+    /// Emoji set to choose from: unicode, gitmoji, devmoji, conventional or custom-<name>. Can be
+    /// cycled through with Ctrl+S [default: unicode].
+    #[arg(long)]
+    set: Option<String>,
+    /// Order the emojis are listed in [default: database].
+    #[arg(long, value_enum)]
+    order: Option<Order>,
+    /// How emojis are selected: `tui`, `fzf`, or `dmenu[:<command>]` for a launcher like dmenu,
+    /// rofi or fuzzel, e.g. `dmenu:rofi -dmenu` [default: tui].
+    #[arg(long)]
+    picker: Option<Picker>,
+    /// Take the emoji from the history without showing the picker, e.g. `last` for the most
+    /// recently selected one. The picker is shown if there's none.
+    #[arg(long, value_enum)]
+    preselect: Option<Preselect>,
+    /// How the selected emoji is copied or inserted: `emoji`, `code`, or a template where
+    /// `{<field>}` is replaced with that field, e.g. `{emoji} ({name})` [default: emoji].
+    #[arg(long)]
+    emoji_format: Option<EmojiFormat>,
+    #[command(flatten)]
+    search: SearchArgs,
+}
 
-    let get_emoji_factory = |color_scheme| {
-        move || {
-            let colors = Colors::from(get_color_scheme(color_scheme));
-            select_emoji(colors)
+/// Options of the emoji search.
+#[derive(Debug, clap::Args)]
+struct SearchArgs {
+    /// How search terms are matched. Can be cycled through with Ctrl+R in the picker [default:
+    /// substring].
+    #[arg(long, value_enum)]
+    match_mode: Option<MatchMode>,
+    /// Shorthand for `--match-mode regex`.
+    #[arg(long, conflicts_with = "match_mode")]
+    regex: bool,
+    /// Only match text of the same case as the search terms.
+    #[arg(long)]
+    case_sensitive: bool,
+    /// Maximum number of typos tolerated per search term, when nothing matches exactly [default:
+    /// 1].
+    #[arg(long)]
+    typo_tolerance: Option<usize>,
+    /// Comma-separated fields searched by terms without a field prefix [default: all].
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<Field>,
+}
+
+impl From<PickerArgs> for Config {
+    fn from(args: PickerArgs) -> Self {
+        Self {
+            color_scheme: args.color_scheme,
+            detect_color_scheme: args.no_detect_color_scheme.then_some(false),
+            set: args.set,
+            order: args.order,
+            picker: args.picker,
+            preselect: args.preselect,
+            emoji_format: args.emoji_format,
+            search: args.search.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<SearchArgs> for SearchConfig {
+    fn from(args: SearchArgs) -> Self {
+        Self {
+            match_mode: if args.regex {
+                Some(MatchMode::Regex)
+            } else {
+                args.match_mode
+            },
+            case_sensitive: args.case_sensitive.then_some(true),
+            typo_tolerance: args.typo_tolerance,
+            fields: (!args.fields.is_empty()).then_some(args.fields),
+            synonyms: None,
         }
+    }
+}
+
+/// An emoji, as output in JSON.
+#[derive(Serialize)]
+struct EmojiOutput {
+    emoji: &'static str,
+    code: &'static str,
+    description: &'static str,
+    name: &'static str,
+    entity: &'static str,
+    codepoints: String,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    category: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    semver: Option<Semver>,
+}
+
+/// An emoji suggested by `gimoji suggest`, as output in JSON.
+#[derive(Serialize)]
+struct SuggestionOutput {
+    #[serde(flatten)]
+    emoji: EmojiOutput,
+    score: i32,
+}
+
+impl From<&'static Emoji> for EmojiOutput {
+    fn from(emoji: &'static Emoji) -> Self {
+        Self {
+            emoji: emoji.emoji,
+            code: emoji.code,
+            description: emoji.description,
+            name: emoji.name,
+            entity: emoji.entity,
+            codepoints: emoji.codepoints(),
+            category: emoji.category,
+            semver: emoji.semver,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let Err(e) = run() else {
+        return ExitCode::SUCCESS;
     };
+    let error = e.downcast_ref::<Error>();
+    // Nothing went wrong if the picker was simply exited.
+    if !matches!(error, Some(Error::Cancelled)) {
+        eprintln!("Error: {e:?}");
+    }
 
+    ExitCode::from(error.map_or(1, Error::exit_code))
+}
+
+fn run() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let profile = args.profile;
+    let strict = args.strict_config;
     match args.cmd {
-        Command::Init { force } => install_hook(force),
-        Command::Copy { color_scheme } => {
-            let Some(emoji) = get_emoji_factory(color_scheme)()? else {
+        Command::Init {
+            force,
+            vcs,
+            compat,
+            daemon,
+        } => {
+            hook::install(force, vcs, compat, daemon).map_err(|e| match e.downcast_ref() {
+                Some(Error::HookAlreadyExists(_)) => {
+                    e.context("Failed to install the hook, use -f to overwrite it")
+                }
+                _ => e,
+            })?;
+            let vcs = match vcs {
+                Vcs::Git => "git",
+                Vcs::Hg => "Mercurial",
+            };
+            println!("Hooked gimoji with {vcs} successfully!");
+
+            Ok(())
+        }
+        Command::InstallAlias {
+            name,
+            scope,
+            force,
+            dry_run,
+        } => {
+            if dry_run {
+                // The command has no single quotes to escape.
+                let command = hook::ALIAS_COMMAND;
+                println!("git config {} alias.{name} '{command}'", scope.option());
+                return Ok(());
+            }
+            hook::install_alias(&name, scope, force)?;
+            println!("Added `git {name}`, committing with an emoji added to the given title.");
+
+            Ok(())
+        }
+        Command::Copy {
+            no_clipboard,
+            clipboard_backend,
+            clipboard_command,
+            clipboard_selection,
+            clipboard_timeout,
+            wait,
+            tmux_buffer,
+            output_file,
+            embedded,
+            picker,
+        } => {
+            let mut cli = Config {
+                clipboard: (no_clipboard || embedded).then_some(false),
+                clipboard_backend,
+                clipboard_command,
+                clipboard_selection,
+                clipboard_timeout,
+                tmux_buffer: tmux_buffer.then_some(true),
+                profile,
+                strict,
+                ..picker.into()
+            };
+            cli.copy_format = cli.emoji_format.clone();
+            let settings = Settings::resolve(cli)?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let format = settings.copy_format.clone();
+            let clipboard = settings.clipboard;
+            let command = settings.clipboard_command.clone();
+            let backend = settings.clipboard_backend.resolve(command.is_some());
+            if matches!(backend, ClipboardBackend::Command) && command.is_none() {
+                bail!("The `command` clipboard backend needs a `clipboard-command`");
+            }
+            let selection = settings.clipboard_selection;
+            let timeout = settings.clipboard_timeout;
+            let tmux_buffer = settings.tmux_buffer && !embedded && clipboard::is_tmux();
+            let Some(emoji) = get_emoji_factory(settings, &database, Screen::Full, None, &[])()?
+            else {
+                // Only tools waiting for the output file need to know, exiting the picker is
+                // otherwise a fine way out of `copy`.
+                if output_file.is_some() || embedded {
+                    return Err(Error::Cancelled.into());
+                }
+                return Ok(());
+            };
+            let text = format.format(emoji);
+            if let Some(path) = &output_file {
+                write_atomically(path, &text)?;
+                if embedded {
+                    return Ok(());
+                }
+            }
+            if tmux_buffer {
+                // Not worth failing over, there's still the clipboard.
+                if let Err(e) = clipboard::copy_to_tmux_buffer(&text) {
+                    eprintln!("WARNING: Failed to load the emoji into a tmux buffer: {e:#}");
+                }
+            }
+            let system = matches!(backend, ClipboardBackend::System);
+            if !clipboard || (system && !clipboard::is_available()) {
+                println!("{text}");
+                return Ok(());
+            }
+            println!("Copied {text} to the clipboard");
+            match (backend, command) {
+                (ClipboardBackend::Osc52, _) => terminal::copy_to_clipboard(&text, selection),
+                (ClipboardBackend::Command, Some(command)) => {
+                    clipboard::copy_with_command(&text, &command)
+                }
+                (ClipboardBackend::Wsl, _) => clipboard::copy_to_windows_clipboard(&text),
+                _ => clipboard::copy_to_clipboard(&text, selection, timeout, wait),
+            }
+        }
+        Command::Print {
+            no_alt_screen,
+            height,
+            output,
+            widget,
+            picker,
+        } => {
+            let mut cli = Config {
+                profile,
+                strict,
+                ..picker.into()
+            };
+            // Meant for commit messages, like the emojis inserted by the hook.
+            cli.hook.emoji_format = output.or(cli.emoji_format.clone());
+            let settings = Settings::resolve(cli)?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let format = settings.hook.emoji_format.clone();
+            let screen = if no_alt_screen {
+                Screen::Inline(height.unwrap_or(DEFAULT_INLINE_HEIGHT))
+            } else {
+                Screen::Full
+            };
+            // The terminal is restored by then, for widgets to redraw the command line right away.
+            let Some(emoji) = get_emoji_factory(settings, &database, screen, None, &[])()? else {
+                return Err(Error::Cancelled.into());
+            };
+            let mut stdout = io::stdout().lock();
+            let line_break = if widget { "" } else { "\n" };
+            write!(stdout, "{}{line_break}", format.format(emoji))
+                .and_then(|()| stdout.flush())
+                .context("Failed to print the emoji")
+        }
+        Command::PrTitle { title, picker } => {
+            let title = if title.is_empty() {
+                io::read_to_string(io::stdin()).context("Failed to read the title")?
+            } else {
+                title.join(" ")
+            };
+            let title = title.trim().to_owned();
+            let mut cli = Config {
+                profile,
+                strict,
+                ..picker.into()
+            };
+            cli.hook.emoji_format = cli.emoji_format.clone();
+            let settings = Settings::resolve(cli)?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let options = HookOptions {
+                convert_types: database.selected_set().has_commit_types(),
+                migrate_codes: settings.hook.migrate_codes,
+                format: settings.hook.emoji_format.clone(),
+                position: settings.hook.position,
+                replace_existing: settings.hook.existing == Existing::Replace,
+                prompt: true,
+            };
+            let get_emoji = get_emoji_factory(settings, &database, Screen::Full, None, &[]);
+            let emojified = hook::emojify(title.clone(), database.emojis(), &options, || {
+                // Rather than leaving the title without one.
+                get_emoji()?.ok_or(Error::Cancelled.into()).map(Some)
+            })?;
+            println!("{}", emojified.unwrap_or(title));
+
+            Ok(())
+        }
+        Command::Search {
+            mut query,
+            category,
+            set,
+            format,
+            search,
+        } => {
+            let settings = Settings::resolve(Config {
+                set,
+                output_format: format,
+                search: search.into(),
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            if let Some(category) = category {
+                query.extend(
+                    category
+                        .split_whitespace()
+                        .map(|word| format!("cat:{word}")),
+                );
+            }
+            search_emojis(
+                &query.join(" "),
+                settings.search,
+                settings.output_format,
+                &database,
+            )
+        }
+        Command::List {
+            category,
+            set,
+            order,
+            format,
+        } => {
+            let settings = Settings::resolve(Config {
+                set,
+                order,
+                output_format: format,
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let set = database
+                .selected_set()
+                .sorted(settings.order, &emoji_uses(&settings));
+            let emojis = set.emojis().iter().copied().filter(|emoji| {
+                category
+                    .as_deref()
+                    .is_none_or(|category| emoji.category.eq_ignore_ascii_case(category))
+            });
+            list_emojis(emojis, settings.output_format)
+        }
+        Command::Bump { set, format } => {
+            let settings = Settings::resolve(Config {
+                // The only built-in set with semver.
+                set: set.or_else(|| Some("gitmoji".to_owned())),
+                output_format: format,
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let recommendation = bump::recommend(database.emojis())?;
+            print_bump(&recommendation, settings.output_format)
+                .context("Failed to write the recommendation")
+        }
+        Command::Suggest {
+            branch,
+            set,
+            format,
+        } => {
+            let settings = Settings::resolve(Config {
+                set,
+                output_format: format,
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let suggester = &settings.suggest.suggester;
+            let mut changes = Changes::staged(suggester.needs_diff())?;
+            if branch.is_some() {
+                changes.branch = branch;
+            }
+            let suggestions = suggester.suggest(&changes);
+            print_suggestions(
+                &suggest::resolve(database.emojis(), &suggestions),
+                settings.output_format,
+            )
+            .context("Failed to write the suggestions")
+        }
+        Command::Undo => {
+            match hook::undo()? {
+                Undone::File(path) => println!("Removed the emoji from `{}`", path.display()),
+                Undone::Head => println!("Removed the emoji from the last commit"),
+            }
+
+            Ok(())
+        }
+        Command::Rebase {
+            range,
+            print,
+            picker,
+        } => {
+            let onto = rebase::onto(&range)?;
+            let mut cli = Config::from(picker);
+            cli.profile = profile;
+            cli.strict = strict;
+            cli.hook.emoji_format = cli.emoji_format.clone();
+            let settings = Settings::resolve(cli)?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let commits = rebase::commits(onto, database.emojis())?;
+            let missing: Box<[_]> = commits
+                .iter()
+                .filter(|commit| !commit.has_emoji)
+                .map(|commit| format!("{} {}", commit.hash, commit.subject))
+                .collect();
+            if missing.is_empty() {
+                println!("All the commits have an emoji already");
                 return Ok(());
+            }
+
+            let uses = emoji_uses(&settings);
+            let sets = database
+                .sets()
+                .iter()
+                .map(|set| set.sorted(settings.order, &uses))
+                .collect();
+            let color_scheme =
+                get_color_scheme(settings.color_scheme, settings.default_color_scheme);
+            let mut picked = terminal::select_emojis(
+                settings.colors(color_scheme),
+                settings.keys,
+                settings.search,
+                sets,
+                database.selected(),
+                missing,
+            )?
+            .into_iter();
+            let plan: Vec<_> = commits
+                .iter()
+                .map(|commit| {
+                    // The emojis were picked for the commits without one, in order.
+                    let emoji = (!commit.has_emoji).then(|| picked.next().flatten());
+                    (commit, emoji.flatten())
+                })
+                .collect();
+            let rewords = plan.iter().filter(|(_, emoji)| emoji.is_some()).count();
+            if rewords == 0 {
+                return Err(Error::Cancelled.into());
+            }
+
+            let todo = rebase::todo(&plan, &settings.hook.emoji_format, settings.hook.position);
+            print!("{todo}");
+            if print {
+                return Ok(());
+            }
+            let answer = terminal::ask(&format!("Reword {rewords} commits?"), "y")?;
+            if !answer.eq_ignore_ascii_case("y") {
+                return Err(Error::Cancelled.into());
+            }
+            rebase::start(onto, &todo)
+        }
+        Command::Reword { msg_file } => rebase::reword(&msg_file),
+        Command::History { cmd, count, format } => {
+            let settings = Settings::resolve(Config {
+                output_format: format,
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let Some(dir) = settings.history.dir else {
+                bail!("The history is disabled, see `history.enabled`");
             };
-            println!("Copied {emoji} to the clipboard");
-            copy_to_clipboard(emoji)
+            match cmd {
+                Some(HistoryCommand::Clear) => History::clear(&dir),
+                None => {
+                    let history = History::load(&dir)?;
+                    let entries: Vec<_> = history.entries.iter().rev().take(count).collect();
+                    print_history(&entries, settings.output_format)
+                        .context("Failed to write the history")
+                }
+            }
+        }
+        Command::Lint {
+            msg_file,
+            set,
+            format,
+        } => {
+            let settings = Settings::resolve(Config {
+                set,
+                output_format: format,
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            let message = match msg_file.filter(|path| path != Path::new("-")) {
+                Some(path) => fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read `{}`", path.display()))?,
+                None => io::read_to_string(io::stdin()).context("Failed to read the message")?,
+            };
+            let violations = lint::lint(&message, database.emojis(), &settings.lint);
+            print_violations(&violations, settings.output_format)
+                .context("Failed to write the violations")?;
+            if !violations.is_empty() {
+                return Err(Error::LintFailed(violations.len()).into());
+            }
+
+            Ok(())
+        }
+        Command::Serve { search } => {
+            let settings = Settings::resolve(Config {
+                search: search.into(),
+                profile,
+                strict,
+                ..Config::default()
+            })?;
+            let database = Database::load(&settings.set, &settings.database)?;
+            serve(&settings, &database)
+        }
+        Command::Update => {
+            let rc = GitmojiRc::load()?;
+            match cache::update(rc.gitmojis_url.as_deref())? {
+                cache::Update::UpToDate => println!("The gitmoji database is already up to date"),
+                cache::Update::Updated => println!("Updated the gitmoji database"),
+            }
+
+            Ok(())
+        }
+        Command::Config {
+            cmd: ConfigCommand::Get { key, file },
+        } => match ConfigFile::from(file).get(&key, profile.as_deref())? {
+            Some(value) => {
+                println!("{value}");
+                Ok(())
+            }
+            None => process::exit(1),
+        },
+        Command::Config {
+            cmd: ConfigCommand::Set { key, value, file },
+        } => ConfigFile::from(file).set(&key, &value, profile.as_deref()),
+        Command::Config {
+            cmd: ConfigCommand::Migrate { file },
+        } => {
+            let file = ConfigFile::from(file);
+            let path = file.path()?;
+            match file.migrate()? {
+                Some(backup) => println!(
+                    "Migrated `{}`, the original is saved as `{}`",
+                    path.display(),
+                    backup.display()
+                ),
+                None => println!("`{}` is up to date", path.display()),
+            }
+
+            Ok(())
+        }
+        Command::Config {
+            cmd: ConfigCommand::Show { origin, picker },
+        } => {
+            let layers = Config::layers(Config {
+                profile,
+                strict,
+                ..picker.into()
+            })?;
+            for (key, value, source) in Config::effective(&layers)? {
+                if origin {
+                    print!("{}\t", source.describe(&key));
+                }
+                println!("{key} = {value}");
+            }
+
+            Ok(())
         }
         Command::Hook {
             msg_file,
             msg_source,
-            color_scheme,
+            vcs,
+            compat,
+            migrate_codes,
+            daemon,
+            picker,
         } => {
-            match msg_source {
-                None | Some(MessageSource::Message | MessageSource::Merge) => {
-                    prepend_emoji(&msg_file, get_emoji_factory(color_scheme))
+            let mut cli = Config::from(picker);
+            cli.profile = profile;
+            cli.strict = strict;
+            cli.hook.migrate_codes = migrate_codes.then_some(true);
+            cli.hook.emoji_format = cli.emoji_format.clone();
+            let settings = Settings::resolve(cli)?;
+            let hook = &settings.hook;
+            // Messages written from scratch have no source.
+            let handled = msg_source.is_none_or(|source| hook.sources.contains(&source));
+            if handled && !(hook.skip_in_ci && hook::is_ci()) {
+                let served = daemon && request_daemon(&msg_file, compat, migrate_codes)?;
+                if !served {
+                    let database = Database::load(&settings.set, &settings.database)?;
+                    run_hook(&msg_file, compat, settings, &database)?;
                 }
-                Some(MessageSource::Template | MessageSource::Squash | MessageSource::Commit) => {
-                    // We do not support any operations for these message types
-                    Ok(())
+            }
+
+            match vcs {
+                Vcs::Git => Ok(()),
+                // The message is yet to be written.
+                Vcs::Hg => hook::run_editor(&msg_file),
+            }
+        }
+        #[cfg(all(unix, feature = "daemon"))]
+        Command::Daemon { picker } => {
+            let mut cli = Config::from(picker);
+            cli.profile = profile;
+            cli.strict = strict;
+            cli.hook.emoji_format = cli.emoji_format.clone();
+            let settings = Settings::resolve(cli)?;
+            let database = Database::load(&settings.set, &settings.database)?;
+
+            daemon::serve(|request| {
+                let mut settings = settings.clone();
+                settings.hook.migrate_codes |= request.migrate_codes;
+                run_hook(&request.msg_file, request.compat, settings, &database)
+            })
+        }
+    }
+}
+
+/// The picker of the settings, to show once the emoji is needed, which records the selection in the
+/// history. The suggested emojis come first.
+fn get_emoji_factory(
+    settings: Settings,
+    database: &Database,
+    screen: Screen,
+    message: Option<String>,
+    suggestions: &[Suggestion],
+) -> impl FnOnce() -> anyhow::Result<Option<&'static Emoji>> {
+    let uses = emoji_uses(&settings);
+    let sets: Box<[EmojiSet]> = database
+        .sets()
+        .iter()
+        .map(|set| suggest::promoted(&set.sorted(settings.order, &uses), suggestions))
+        .collect();
+    let selected = database.selected();
+    move || {
+        let preselected = match settings.preselect {
+            Some(Preselect::Last) => {
+                last_selection(settings.history.dir.as_deref(), &sets, selected)
+            }
+            None => None,
+        };
+        let emoji = match &settings.picker {
+            _ if preselected.is_some() => preselected,
+            Picker::Tui => {
+                let color_scheme =
+                    get_color_scheme(settings.color_scheme, settings.default_color_scheme);
+                let history = &settings.history;
+                let query_dir = history.dir.as_deref().filter(|_| history.remember_query);
+                let mut query = match query_dir.map(History::load_query) {
+                    Some(Ok(query)) => query,
+                    Some(Err(e)) => {
+                        eprintln!("WARNING: Failed to restore the last search: {e:#}");
+                        String::new()
+                    }
+                    None => String::new(),
+                };
+                let emoji = terminal::select_emoji(
+                    settings.colors(color_scheme),
+                    settings.keys,
+                    settings.search,
+                    sets,
+                    selected,
+                    screen,
+                    &mut query,
+                    message,
+                )?;
+                if let Some(dir) = query_dir {
+                    // Not worth failing over, the search is done.
+                    if let Err(e) = History::save_query(dir, &query) {
+                        eprintln!("WARNING: Failed to save the last search: {e:#}");
+                    }
                 }
+
+                emoji
+            }
+            Picker::Dmenu(command) => picker::select_with_dmenu(command, &sets[selected])?,
+            Picker::Fzf => picker::select_with_fzf(&sets[selected])?,
+        };
+        if let (Some(emoji), Some(dir)) = (emoji, &settings.history.dir) {
+            // Not worth failing over, the emoji was selected after all.
+            if let Err(e) = History::record(dir, emoji, settings.history.retention) {
+                eprintln!("WARNING: Failed to record the selection in the history: {e:#}");
             }
         }
+
+        Ok(emoji)
     }
 }
 
-fn select_emoji(colors: Colors) -> anyhow::Result<Option<&'static str>> {
-    let mut terminal = Terminal::new(colors)?;
-    loop {
-        let response = terminal.render_ui()?;
-        match response {
-            EventResponse::Noop => {}
-            EventResponse::EmojiSelected(emoji) => return terminal.reset().map(|()| Some(emoji)),
-            EventResponse::Exit => return terminal.reset().map(|()| None),
+/// Add the emoji to the commit message in the file, or write it from gitmoji-cli's questions.
+fn run_hook(
+    msg_file: &Path,
+    compat: Option<Compat>,
+    settings: Settings,
+    database: &Database,
+) -> anyhow::Result<()> {
+    let hook = &settings.hook;
+    // Launchers don't need one.
+    let prompt =
+        !hook.skip_without_tty || settings.picker != Picker::Tui || terminal::is_available();
+    let options = HookOptions {
+        convert_types: database.selected_set().has_commit_types(),
+        migrate_codes: hook.migrate_codes,
+        format: hook.emoji_format.clone(),
+        position: hook.position,
+        replace_existing: hook.existing == Existing::Replace,
+        prompt,
+    };
+    // Not worth failing over, the hook reads it again anyway.
+    let message = fs::read_to_string(msg_file)
+        .ok()
+        .and_then(|message| hook::shown_message(&message, hook.show_message));
+    let suggest = &settings.suggest;
+    let suggestions = match suggest.enabled {
+        true => suggest.suggester.for_commit(),
+        false => Vec::new(),
+    };
+    let get_emoji = get_emoji_factory(settings, database, Screen::Full, message, &suggestions);
+    match compat {
+        None => hook::add_emoji(msg_file, database.emojis(), options, get_emoji),
+        Some(Compat::GitmojiCli) if prompt => {
+            let rc = GitmojiRc::load()?;
+            hook::write_gitmoji_cli_message(
+                msg_file,
+                database.emojis(),
+                rc.capitalize_title.unwrap_or(true),
+                |title| ask_gitmoji_cli(&rc, title, get_emoji),
+            )
         }
+        Some(Compat::GitmojiCli) => Ok(()),
     }
 }
 
-fn install_hook(force: bool) -> anyhow::Result<()> {
-    fs::create_dir_all(HOOK_FOLDER).context("Failed to create hooks dir")?;
-    let file_path = Path::new(HOOK_FOLDER).join(PRE_COMMIT_MSG_HOOK);
+/// Have the daemon run the hook on this terminal, returning whether it did, which it can't without
+/// a terminal or if it's not running.
+#[cfg(all(unix, feature = "daemon"))]
+fn request_daemon(
+    msg_file: &Path,
+    compat: Option<Compat>,
+    migrate_codes: bool,
+) -> anyhow::Result<bool> {
+    let Some(tty) = daemon::tty() else {
+        return Ok(false);
+    };
+    let request = daemon::Request {
+        tty,
+        dir: std::env::current_dir().context("Failed to get the current directory")?,
+        msg_file: msg_file.to_owned(),
+        compat,
+        migrate_codes,
+    };
+
+    Ok(daemon::request(&request)?.is_some())
+}
+
+#[cfg(not(all(unix, feature = "daemon")))]
+fn request_daemon(_: &Path, _: Option<Compat>, _: bool) -> anyhow::Result<bool> {
+    // There's no daemon without its feature.
+    Ok(false)
+}
+
+/// The emoji selected last according to the history in the directory, if it's in one of the sets,
+/// looked up in the selected one first.
+fn last_selection(
+    dir: Option<&Path>,
+    sets: &[EmojiSet],
+    selected: usize,
+) -> Option<&'static Emoji> {
+    let history = match History::load(dir?) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("WARNING: Failed to read the history: {e:#}");
+            return None;
+        }
+    };
+    let code = &history.entries.last()?.code;
 
-    let mut options = OpenOptions::new();
-    options.write(true).create(true);
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::OpenOptionsExt;
-        options.mode(0o744);
+    sets[selected..]
+        .iter()
+        .chain(&sets[..selected])
+        .flat_map(EmojiSet::emojis)
+        .find(|emoji| emoji.code == code)
+        .copied()
+}
+
+/// How many times each emoji was selected according to the history, by code, which is only read to
+/// list the emojis by usage.
+fn emoji_uses(settings: &Settings) -> HashMap<String, usize> {
+    let dir = settings.history.dir.as_deref();
+    let Some(dir) = dir.filter(|_| settings.order == Order::Usage) else {
+        return HashMap::new();
+    };
+
+    match History::load(dir) {
+        Ok(history) => history.uses(),
+        Err(e) => {
+            eprintln!("WARNING: Failed to read the history: {e:#}");
+            HashMap::new()
+        }
     }
+}
 
-    let file = match options.open(&file_path) {
-        Ok(f) => f,
-        Err(e) if e.kind() == ErrorKind::AlreadyExists && !force => {
-            bail!(
-                "Failed to create `{}` as it already exists. Use -f to force overwrite it.",
-                file_path.display()
-            )
+/// Ask gitmoji-cli's questions, in its order, as set in its config.
+fn ask_gitmoji_cli(
+    rc: &GitmojiRc,
+    title: &str,
+    get_emoji: impl FnOnce() -> anyhow::Result<Option<&'static Emoji>>,
+) -> anyhow::Result<Option<CommitAnswers>> {
+    let Some(emoji) = get_emoji()? else {
+        return Ok(None);
+    };
+    // gitmoji-cli writes codes unless told otherwise.
+    let emoji = rc
+        .emoji_format
+        .as_ref()
+        .unwrap_or(&EmojiFormat::Code)
+        .format(emoji);
+    let scope = match rc.scope_prompt.unwrap_or(false) {
+        true => Some(terminal::ask("Enter the scope of current changes:", "")?),
+        false => None,
+    };
+    let title = loop {
+        let title = terminal::ask("Enter the commit title:", title)?;
+        if !title.is_empty() {
+            break title;
         }
-        Err(e) => return Err(anyhow::anyhow!(e)).context("Failed to create hook file"),
+        eprintln!("Enter a valid commit title");
+    };
+    let message = match rc.message_prompt.unwrap_or(true) {
+        true => Some(terminal::ask("Enter the commit message:", "")?),
+        false => None,
     };
 
-    let mut writer = BufWriter::new(file);
-    writer
-        .write_all(HOOK_HEADER.as_bytes())
-        .context("Failed to write hook header")?;
-    writer
-        .write_all(HOOK_CMD.as_bytes())
-        .context("Failed to write hook command")?;
-    writer.flush().context("Failed to flush hook buffer")?;
-
-    Ok(println!("Hooked gimoji with git successfully!"))
-}
-
-/// Copy the text to the clipboard.
-///
-/// This function exits the process and never returns because on some platforms (X11, Wayland)
-/// clipboard data is only available for as long as the process that "owns" it is alive, in which
-/// case this function will spawn a background task to host the clipboard data.
-///
-/// Note that it is possible to make it work without exiting the process, but it would require an
-/// `unsafe { fork() }`. However, in this program this is simply not needed.
-fn copy_to_clipboard(emoji: &str) -> anyhow::Result<()> {
-    macro_rules! clipboard {
-        () => {
-            Clipboard::new()
-                .context("Failed to create clipboard instance")?
-                .set()
-        };
+    Ok(Some(CommitAnswers {
+        emoji,
+        scope: scope.filter(|scope| !scope.is_empty()),
+        title,
+        message: message.filter(|message| !message.is_empty()),
+    }))
+}
+
+fn print_bump(recommendation: &Recommendation, format: OutputFormat) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        serde_json::to_writer_pretty(&mut stdout, recommendation)?;
+        return writeln!(stdout);
     }
 
-    macro_rules! paste_text {
-        ($set:expr) => {
-            $set.text(emoji)
-                .context("Failed to copy emoji to clipboard")?
-        };
+    let bump = match recommendation.bump {
+        Some(Semver::Major) => "major",
+        Some(Semver::Minor) => "minor",
+        Some(Semver::Patch) => "patch",
+        None => "none",
+    };
+    writeln!(stdout, "{bump}")?;
+    for commit in recommendation.driving_commits() {
+        writeln!(stdout, "{} {}", commit.hash, commit.subject)?;
     }
 
-    cfg_if::cfg_if! {
-        if #[cfg(any(
-            target_os = "dragonfly",
-            target_os = "freebsd",
-            target_os = "illumos",
-            target_os = "linux",
-            target_os = "netbsd",
-            target_os = "openbsd",
-            target_os = "solaris"
-        ))]
-        {
-            use arboard::SetExtLinux;
-            nix::unistd::daemon(false, false).context("Failed to daemonize process")?;
-            paste_text!(clipboard!().wait())
-        } else {
-            paste_text!(clipboard!())
+    Ok(())
+}
+
+fn print_suggestions(
+    suggestions: &[(&'static Emoji, i32)],
+    format: OutputFormat,
+) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        let suggestions: Vec<_> = suggestions
+            .iter()
+            .map(|&(emoji, score)| SuggestionOutput {
+                emoji: emoji.into(),
+                score,
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut stdout, &suggestions)?;
+        return writeln!(stdout);
+    }
+
+    for (emoji, score) in suggestions {
+        writeln!(
+            stdout,
+            "{} {} {} ({score})",
+            emoji.emoji, emoji.code, emoji.description
+        )?;
+    }
+
+    Ok(())
+}
+
+fn print_history(entries: &[&Entry], format: OutputFormat) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        serde_json::to_writer_pretty(&mut stdout, entries)?;
+        return writeln!(stdout);
+    }
+
+    for entry in entries {
+        write!(
+            stdout,
+            "{}  {} {}",
+            format_timestamp(entry.timestamp),
+            entry.emoji,
+            entry.code
+        )?;
+        match &entry.repo {
+            Some(repo) => writeln!(stdout, "  {}", repo.display())?,
+            None => writeln!(stdout)?,
         }
     }
 
-    process::exit(0)
+    Ok(())
 }
 
-// Color scheme selection. Precedence: env, arg, detection, default.
-fn get_color_scheme(color_scheme_arg: Option<ColorScheme>) -> ColorScheme {
-    std::env::var("GIMOJI_COLOR_SCHEME")
-        .ok()
-        .and_then(|s| match s.as_str() {
-            "light" => Some(ColorScheme::Light),
-            "dark" => Some(ColorScheme::Dark),
-            _ => None,
-        })
-        .or(color_scheme_arg)
-        .unwrap_or_else(|| {
-            terminal_light::luma()
-                .map(|l| {
-                    if l > 0.6 {
-                        ColorScheme::Light
-                    } else {
-                        ColorScheme::Dark
-                    }
-                })
-                .unwrap_or_else(|e| {
-                    eprintln!("WARNING: Failed to detect terminal luma: {e}. Assuming dark.");
+/// The time of the Unix timestamp, in UTC, e.g. `2024-08-04 17:30`.
+fn format_timestamp(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / SECONDS_PER_DAY, timestamp % SECONDS_PER_DAY);
+    // The civil date of the days since the epoch, counted in 400-year eras starting in March so
+    // that leap days come last.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
 
-                    ColorScheme::Dark
-                })
-        })
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
 }
 
-fn prepend_emoji(
-    path: &Path,
-    get_emoji: impl FnOnce() -> anyhow::Result<Option<&'static str>>,
+fn print_violations(violations: &[Violation], format: OutputFormat) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        serde_json::to_writer_pretty(&mut stdout, violations)?;
+        return writeln!(stdout);
+    }
+
+    for violation in violations {
+        writeln!(
+            stdout,
+            "{} (lint.{})",
+            violation.message,
+            violation.rule.name()
+        )?;
+    }
+
+    Ok(())
+}
+
+fn search_emojis(
+    query: &str,
+    options: SearchOptions,
+    format: OutputFormat,
+    database: &Database,
 ) -> anyhow::Result<()> {
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(false)
-        .open(path)
-        .context("Failed to open commit msg file in r/w mode")?;
-
-    let file_size = file
-        .metadata()
-        .context("Failed to get commit msg file metadata")?
-        .len() as usize;
-
-    let mut reader = BufReader::new(file);
-    let mut content = String::new();
-    reader
-        .read_line(&mut content)
-        .context("Failed to read first line in commit msg file")?;
-
-    if !content.is_empty() {
-        // FIXME: There has to be a faster way to detect an emoji.
-        for emoji in emoji::EMOJIS {
-            if content.contains(emoji.emoji) || content.contains(emoji.code) {
-                // The commit shortlog already contains an emoji.
-                return Ok(());
+    let query = Query::parse(query, &options).context("Invalid search query")?;
+    let matches = query.filter(database.emojis());
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        let emojis: Vec<_> = matches
+            .emojis(database.emojis())
+            .map(EmojiOutput::from)
+            .collect();
+        serde_json::to_writer_pretty(&mut stdout, &emojis)
+            .context("Failed to write search results")?;
+        return writeln!(stdout).context("Failed to write search results");
+    }
+
+    let underline = stdout.is_terminal();
+    for emoji in matches.emojis(database.emojis()) {
+        let field = |field| {
+            let text = emoji.field(field);
+            if underline {
+                underline_ranges(text, &query.highlights(emoji, field))
+            } else {
+                text.to_owned()
             }
+        };
+        writeln!(
+            stdout,
+            "{} {} {}",
+            emoji.emoji,
+            field(Field::Code),
+            field(Field::Description)
+        )
+        .context("Failed to write search results")?;
+    }
+
+    Ok(())
+}
+
+fn list_emojis(
+    emojis: impl Iterator<Item = &'static Emoji>,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if let OutputFormat::Json = format {
+        let emojis: Vec<_> = emojis.map(EmojiOutput::from).collect();
+        serde_json::to_writer_pretty(&mut stdout, &emojis).context("Failed to write the emojis")?;
+        return writeln!(stdout).context("Failed to write the emojis");
+    }
+
+    for emoji in emojis {
+        writeln!(
+            stdout,
+            "{} {} {}",
+            emoji.emoji, emoji.code, emoji.description
+        )
+        .context("Failed to write the emojis")?;
+    }
+
+    Ok(())
+}
+
+/// A request of `serve`, with an `id` of any type echoed back in its response, if given.
+#[derive(Deserialize)]
+struct ServeRequest {
+    #[serde(default)]
+    id: serde_json::Value,
+    #[serde(flatten)]
+    command: ServeCommand,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum ServeCommand {
+    /// The name and version of gimoji, and the version of the protocol.
+    Handshake,
+    /// The emoji sets, with the default one selected.
+    ListSets,
+    /// The emojis of the set, the default one if not given, matching the query.
+    Search {
+        query: String,
+        set: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Record the emoji of the set picked by the user in the history.
+    RecordSelection { code: String, set: Option<String> },
+}
+
+/// The response to a request, with either its `result` or an `error` message.
+#[derive(Serialize)]
+struct ServeResponse {
+    id: serde_json::Value,
+    #[serde(flatten)]
+    outcome: ServeOutcome,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ServeOutcome {
+    Result(serde_json::Value),
+    Error(String),
+}
+
+/// Answer the requests read from stdin, one per line, until it's closed.
+fn serve(settings: &Settings, database: &Database) -> anyhow::Result<()> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lines() {
+        let line = line.context("Failed to read request")?;
+        if line.trim().is_empty() {
+            continue;
         }
+
+        let response = match serde_json::from_str::<ServeRequest>(&line) {
+            Ok(request) => ServeResponse {
+                id: request.id,
+                outcome: match answer(request.command, settings, database) {
+                    Ok(result) => ServeOutcome::Result(result),
+                    Err(e) => ServeOutcome::Error(format!("{e:#}")),
+                },
+            },
+            Err(e) => ServeResponse {
+                id: serde_json::Value::Null,
+                outcome: ServeOutcome::Error(format!("Invalid request: {e}")),
+            },
+        };
+        serde_json::to_writer(&mut stdout, &response).context("Failed to write response")?;
+        writeln!(stdout)
+            .and_then(|()| stdout.flush())
+            .context("Failed to write response")?;
     }
 
-    let Some(emoji) = get_emoji()? else {
-        return Ok(());
+    Ok(())
+}
+
+fn answer(
+    command: ServeCommand,
+    settings: &Settings,
+    database: &Database,
+) -> anyhow::Result<serde_json::Value> {
+    let find_set = |name: Option<String>| match name {
+        Some(name) => database
+            .sets()
+            .iter()
+            .find(|set| set.name == name)
+            .with_context(|| format!("Unknown emoji set `{name}`")),
+        None => Ok(database.selected_set()),
     };
 
-    let mut content = content.into_bytes();
-    content.reserve(file_size - content.len());
-    reader
-        .read_to_end(&mut content)
-        .context("Failed to read rest of the commit msg file")?;
-    reader
-        .seek(SeekFrom::Start(0))
-        .context("Failed to seek to start of commit msg file")?;
-
-    let mut writer = BufWriter::new(reader.into_inner());
-    write!(&mut writer, "{emoji} ").context("Failed to write emoji to buffer")?;
-    writer
-        .write_all(&content)
-        .context("Failed to write commit message to buffer")?;
-    writer.flush().context("Failed to flush commit msg buffer")
-}
-
-const HOOK_FOLDER: &str = ".git/hooks";
-const PRE_COMMIT_MSG_HOOK: &str = "prepare-commit-msg";
-const HOOK_HEADER: &str = "#!/usr/bin/env bash\n# gimoji as a commit hook\n";
-const HOOK_CMD: &str = r#"gimoji hook $1 $2"#;
+    let result = match command {
+        ServeCommand::Handshake => json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": SERVE_PROTOCOL_VERSION,
+        }),
+        ServeCommand::ListSets => database
+            .sets()
+            .iter()
+            .enumerate()
+            .map(|(i, set)| {
+                json!({
+                    "name": set.name,
+                    "emojis": set.emojis().len(),
+                    "selected": i == database.selected(),
+                })
+            })
+            .collect(),
+        ServeCommand::Search { query, set, limit } => {
+            let emojis = find_set(set)?.emojis();
+            let query = Query::parse(&query, &settings.search).context("Invalid search query")?;
+            let matches: Vec<_> = query
+                .filter(emojis)
+                .emojis(emojis)
+                .take(limit.unwrap_or(usize::MAX))
+                .map(EmojiOutput::from)
+                .collect();
+
+            serde_json::to_value(matches).expect("Emojis are valid JSON")
+        }
+        ServeCommand::RecordSelection { code, set } => {
+            let emoji = find_set(set)?
+                .emojis()
+                .iter()
+                .find(|emoji| emoji.code == code)
+                .with_context(|| format!("Unknown emoji `{code}`"))?;
+            if let Some(dir) = &settings.history.dir {
+                History::record(dir, emoji, settings.history.retention)?;
+            }
+
+            serde_json::Value::Null
+        }
+    };
+
+    Ok(result)
+}
+
+/// Underline the given byte ranges of the text, using ANSI escape sequences.
+fn underline_ranges(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut underlined = String::with_capacity(text.len());
+    let mut end = 0;
+    for range in ranges {
+        underlined.push_str(&text[end..range.start]);
+        underlined.push_str(UNDERLINE_ON);
+        underlined.push_str(&text[range.clone()]);
+        underlined.push_str(UNDERLINE_OFF);
+        end = range.end;
+    }
+    underlined.push_str(&text[end..]);
+
+    underlined
+}
+
+const DEFAULT_INLINE_HEIGHT: u16 = 15;
+/// The version of the `serve` protocol, increased with incompatible changes.
+const SERVE_PROTOCOL_VERSION: u32 = 1;
+const UNDERLINE_ON: &str = "\x1b[4m";
+const UNDERLINE_OFF: &str = "\x1b[24m";
+
+/// Write the file through a temporary one replacing it once complete, so that it's never read
+/// half-written.
+fn write_atomically(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{file_name}.{}.tmp", process::id()));
+    fs::write(&temp, contents)
+        .and_then(|()| fs::rename(&temp, path))
+        .inspect_err(|_| {
+            // Not worth keeping around.
+            let _ = fs::remove_file(&temp);
+        })
+        .with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+// Color scheme selection. Precedence: settings, detection, default.
+#[cfg(feature = "luma-detect")]
+fn get_color_scheme(color_scheme: Option<ColorScheme>, default: ColorScheme) -> ColorScheme {
+    color_scheme.unwrap_or_else(|| {
+        // Querying the terminal is slow, especially over SSH, hence the cache.
+        let luma = match cache::luma() {
+            Some(luma) => Ok(luma),
+            None => terminal_light::luma().inspect(|&luma| {
+                // Not worth warning about, it's only to be faster next time.
+                let _ = cache::set_luma(luma);
+            }),
+        };
+        luma.map(|l| {
+            if l > 0.6 {
+                ColorScheme::Light
+            } else {
+                ColorScheme::Dark
+            }
+        })
+        .unwrap_or_else(|e| {
+            let name = format!("{default:?}").to_lowercase();
+            eprintln!("WARNING: Failed to detect terminal luma: {e}. Assuming {name}.");
+
+            default
+        })
+    })
+}
+
+// Without detection, as if `detect-color-scheme` was false.
+#[cfg(not(feature = "luma-detect"))]
+fn get_color_scheme(color_scheme: Option<ColorScheme>, default: ColorScheme) -> ColorScheme {
+    color_scheme.unwrap_or(default)
+}
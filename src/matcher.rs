@@ -0,0 +1,173 @@
+use std::{fmt, ops::Range};
+
+use memchr::memmem::Finder;
+use regex::Regex;
+
+/// Something emoji fields are matched against.
+///
+/// Matchers are given the normalized, lowercase fields of emojis.
+pub trait Matcher {
+    /// Whether the text matches.
+    fn is_match(&self, text: &str) -> bool;
+
+    /// The byte ranges of all the matches in the text, in order and without overlaps.
+    fn find_ranges(&self, text: &str) -> Vec<Range<usize>>;
+}
+
+/// The kind of [`Matcher`] search terms are turned into.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchMode {
+    /// Match the term as is.
+    #[default]
+    Substring,
+    /// Match the characters of the term in order, but not necessarily next to each other.
+    Fuzzy,
+    /// Match the term as a regular expression.
+    Regex,
+}
+
+impl MatchMode {
+    /// The next mode, for cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Substring => Self::Fuzzy,
+            Self::Fuzzy => Self::Regex,
+            Self::Regex => Self::Substring,
+        }
+    }
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Substring => "substring",
+            Self::Fuzzy => "fuzzy",
+            Self::Regex => "regex",
+        })
+    }
+}
+
+impl Matcher for Regex {
+    fn is_match(&self, text: &str) -> bool {
+        Regex::is_match(self, text)
+    }
+
+    fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.find_iter(text)
+            .map(|m| m.range())
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+}
+
+/// Matches text containing a (lowercase) needle.
+///
+/// The needle is searched with a vectorized searcher built once, as matching all the emojis runs
+/// it thousands of times.
+#[derive(Debug)]
+pub struct Substring(Finder<'static>);
+
+impl Substring {
+    pub fn new(needle: &str) -> Self {
+        Self(Finder::new(needle).into_owned())
+    }
+}
+
+impl Matcher for Substring {
+    fn is_match(&self, text: &str) -> bool {
+        self.0.find(text.as_bytes()).is_some()
+    }
+
+    fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let len = self.0.needle().len();
+        if len == 0 {
+            return Vec::new();
+        }
+
+        self.0
+            .find_iter(text.as_bytes())
+            .map(|idx| idx..idx + len)
+            .collect()
+    }
+}
+
+/// Matches text containing all the characters of a (lowercase) pattern, in order.
+#[derive(Debug)]
+pub struct Fuzzy(pub String);
+
+impl Fuzzy {
+    /// The byte range of each character of the pattern at its leftmost position in the text after
+    /// the previous one, if they're all there.
+    ///
+    /// Characters are searched as their UTF-8 bytes, which only match whole characters.
+    fn positions<'t>(&'t self, text: &'t str) -> impl Iterator<Item = Option<Range<usize>>> + 't {
+        let mut start = 0;
+        let mut buf = [0; 4];
+        self.0.chars().map(move |c| {
+            let bytes = c.encode_utf8(&mut buf).as_bytes();
+            let rest = &text.as_bytes()[start..];
+            let idx = match bytes {
+                [byte] => memchr::memchr(*byte, rest),
+                _ => memchr::memmem::find(rest, bytes),
+            }?;
+            let range = start + idx..start + idx + bytes.len();
+            start = range.end;
+
+            Some(range)
+        })
+    }
+}
+
+impl Matcher for Fuzzy {
+    fn is_match(&self, text: &str) -> bool {
+        self.positions(text).all(|range| range.is_some())
+    }
+
+    fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.positions(text)
+            .collect::<Option<_>>()
+            .map(merge_ranges)
+            .unwrap_or_default()
+    }
+}
+
+/// Matches if any of the matchers does.
+impl<M: Matcher> Matcher for Vec<M> {
+    fn is_match(&self, text: &str) -> bool {
+        self.iter().any(|matcher| matcher.is_match(text))
+    }
+
+    fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        merge_ranges(
+            self.iter()
+                .flat_map(|matcher| matcher.find_ranges(text))
+                .collect(),
+        )
+    }
+}
+
+/// Sort the ranges and merge the overlapping or adjacent ones.
+pub fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_unstable_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
@@ -0,0 +1,45 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, Padding, Paragraph, Widget},
+};
+
+use crate::colors::Colors;
+
+/// The commit message the emoji is picked for, shown above the picker.
+pub struct MessagePane {
+    message: String,
+    colors: Colors,
+}
+
+impl MessagePane {
+    pub fn new(colors: Colors, message: String) -> Self {
+        Self { message, colors }
+    }
+
+    /// The rows the pane takes, borders included, with long messages cut short.
+    pub fn height(&self) -> u16 {
+        let lines = self.message.lines().count().clamp(1, MAX_LINES);
+        // Fits in a u16 as it's clamped.
+        lines as u16 + 2
+    }
+}
+
+impl Widget for &MessagePane {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Paragraph::new(self.message.as_str())
+            .block(
+                Block::default()
+                    .title(TITLE)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.colors.border))
+                    .padding(Padding::horizontal(1)),
+            )
+            .style(Style::default().fg(self.colors.unselected))
+            .render(area, buf);
+    }
+}
+
+const TITLE: &str = "Commit message";
+const MAX_LINES: usize = 6;
@@ -0,0 +1,17 @@
+use std::borrow::Cow;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Normalize text for matching.
+///
+/// The text is decomposed (NFKD) and stripped of all combining marks, so that e.g. `déploiement`
+/// and `deploiement` or `ﬁ` and `fi` compare equal. Both the search text and the searched fields go
+/// through this.
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        // Nothing to decompose or strip.
+        return Cow::Borrowed(text);
+    }
+
+    Cow::Owned(text.nfkd().filter(|c| !is_combining_mark(*c)).collect())
+}
@@ -0,0 +1,46 @@
+use std::{env, path::PathBuf};
+
+use directories::{BaseDirs, ProjectDirs};
+
+/// The directory holding the user's gimoji configuration, e.g. `~/.config/gimoji` on Linux, unless
+/// set through `GIMOJI_CONFIG_DIR`.
+pub fn config_dir() -> Option<PathBuf> {
+    env_dir(CONFIG_DIR_ENV).or_else(|| project_dirs().map(|dirs| dirs.config_dir().to_owned()))
+}
+
+/// The directory holding the data gimoji records, e.g. `~/.local/share/gimoji` on Linux, unless set
+/// through `GIMOJI_DATA_DIR`.
+pub fn data_dir() -> Option<PathBuf> {
+    env_dir(DATA_DIR_ENV).or_else(|| project_dirs().map(|dirs| dirs.data_dir().to_owned()))
+}
+
+/// The directory holding gimoji's cached data, e.g. `~/.cache/gimoji` on Linux.
+pub fn cache_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().to_owned())
+}
+
+/// The directory for the user's sockets and the like, e.g. `$XDG_RUNTIME_DIR/gimoji` on Linux,
+/// which other platforms don't have.
+#[cfg(all(unix, feature = "daemon"))]
+pub fn runtime_dir() -> Option<PathBuf> {
+    project_dirs().and_then(|dirs| dirs.runtime_dir().map(ToOwned::to_owned))
+}
+
+/// The user's home directory.
+pub fn home_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.home_dir().to_owned())
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "gimoji")
+}
+
+/// The directory set by the environment variable, if it's set and not empty.
+fn env_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+const CONFIG_DIR_ENV: &str = "GIMOJI_CONFIG_DIR";
+const DATA_DIR_ENV: &str = "GIMOJI_DATA_DIR";
@@ -0,0 +1,196 @@
+use std::{
+    fmt,
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::EmojiSet,
+    emoji::{Emoji, Semver},
+};
+
+/// How emojis are selected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Picker {
+    /// The picker of [`terminal`](crate::terminal).
+    #[default]
+    Tui,
+    /// A launcher like dmenu, rofi or fuzzel, run by the shell with the given command, which is
+    /// given the emojis on stdin, one per line, and prints the chosen one.
+    Dmenu(String),
+    /// fzf, with the details of the emojis in its preview window. Its usual settings, e.g.
+    /// `FZF_DEFAULT_OPTS`, apply.
+    Fzf,
+}
+
+impl FromStr for Picker {
+    type Err = String;
+
+    /// `tui`, `fzf`, or `dmenu` optionally followed by `:<command>`, e.g. `dmenu:rofi -dmenu`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tui" => Ok(Self::Tui),
+            "fzf" => Ok(Self::Fzf),
+            "dmenu" => Ok(Self::Dmenu(DEFAULT_DMENU_COMMAND.to_owned())),
+            _ => match s.strip_prefix("dmenu:") {
+                Some(command) if !command.trim().is_empty() => Ok(Self::Dmenu(command.to_owned())),
+                _ => Err(format!(
+                    "unknown picker `{s}`, expected `tui`, `fzf` or `dmenu[:<command>]`"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Picker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tui => f.write_str("tui"),
+            Self::Dmenu(command) => write!(f, "dmenu:{command}"),
+            Self::Fzf => f.write_str("fzf"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Picker {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let picker = String::deserialize(deserializer)?;
+
+        picker.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Picker {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Select an emoji of the set with a dmenu-like launcher, run by the shell with the given command.
+pub fn select_with_dmenu(command: &str, set: &EmojiSet) -> anyhow::Result<Option<&'static Emoji>> {
+    let lines: Vec<_> = set.emojis().iter().map(|emoji| candidate(emoji)).collect();
+    let Some(chosen) = choose(shell(command), command, &lines)? else {
+        return Ok(None);
+    };
+    let position = lines.iter().position(|line| *line == chosen);
+    // Some launchers let the user edit the line, whose emoji or code is then enough.
+    let emoji = match position {
+        Some(i) => set.emojis()[i],
+        None => {
+            let first = chosen.split_whitespace().next().unwrap_or_default();
+            let found = set
+                .emojis()
+                .iter()
+                .find(|emoji| emoji.emoji == first || emoji.code == first);
+            match found {
+                Some(emoji) => emoji,
+                None => bail!("`{command}` printed `{chosen}`, which is not one of the emojis"),
+            }
+        }
+    };
+
+    Ok(Some(emoji))
+}
+
+/// Select an emoji of the set with fzf, which shows the emojis like launchers do, and the rest of
+/// their details in its preview window.
+pub fn select_with_fzf(set: &EmojiSet) -> anyhow::Result<Option<&'static Emoji>> {
+    let lines: Vec<_> = set
+        .emojis()
+        .iter()
+        .map(|emoji| {
+            let semver = match emoji.semver {
+                Some(Semver::Major) => "major",
+                Some(Semver::Minor) => "minor",
+                Some(Semver::Patch) => "patch",
+                None => "none",
+            };
+            // The details, in fields of their own that are only shown in the preview.
+            format!(
+                "{}\tName: {}\tEntity: {}\tCategory: {}\tTags: {}\tSemver: {semver}",
+                candidate(emoji),
+                emoji.name,
+                emoji.entity,
+                emoji.category,
+                emoji.tags
+            )
+        })
+        .collect();
+    let mut fzf = Command::new(FZF_COMMAND);
+    fzf.args([
+        "--delimiter=\t",
+        "--with-nth=1",
+        "--preview=printf '%s\\n' {1} {2} {3} {4} {5} {6}",
+        "--preview-window=down,7,wrap",
+    ]);
+
+    let chosen = choose(fzf, FZF_COMMAND, &lines)?;
+    Ok(chosen.and_then(|chosen| {
+        let i = lines.iter().position(|line| *line == chosen)?;
+        Some(set.emojis()[i])
+    }))
+}
+
+/// The line chosen among the given ones by the command, which is given them on stdin and prints
+/// the chosen one, if any.
+///
+/// Commands exit with a failure when nothing is chosen, which is taken as no selection.
+fn choose(mut command: Command, name: &str, lines: &[String]) -> anyhow::Result<Option<String>> {
+    let mut child = match command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => bail!("`{name}` not found"),
+        Err(e) => return Err(e).with_context(|| format!("Failed to run `{name}`")),
+    };
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = lines.join("\n");
+    // Written from another thread, as the launcher may not read everything before printing.
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run `{name}`"))?;
+    // Launchers may exit as soon as something is chosen, without reading the rest.
+    let _ = writer.join();
+    // As told by the shell.
+    if output.status.code() == Some(COMMAND_NOT_FOUND) {
+        bail!("`{name}` not found");
+    }
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    let chosen = chosen.trim_end_matches(['\r', '\n']);
+
+    Ok((!chosen.is_empty()).then(|| chosen.to_owned()))
+}
+
+/// The line of the emoji given to launchers.
+fn candidate(emoji: &Emoji) -> String {
+    format!("{} {} {}", emoji.emoji, emoji.code, emoji.description)
+}
+
+/// The command, run by the shell.
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+
+    shell
+}
+
+const DEFAULT_DMENU_COMMAND: &str = "dmenu";
+const FZF_COMMAND: &str = "fzf";
+const COMMAND_NOT_FOUND: i32 = 127;
@@ -0,0 +1,591 @@
+use std::{ops::Range, slice};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    emoji::{Emoji, Field},
+    matcher::{merge_ranges, Fuzzy, MatchMode, Matcher, Substring},
+    normalize::normalize,
+    synonyms::Synonyms,
+};
+
+/// How search text is turned into a [`Query`].
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// How terms are matched.
+    pub mode: MatchMode,
+    /// Synonyms literal terms are expanded to.
+    pub synonyms: Synonyms,
+    /// Whether terms only match text of the same case.
+    pub case_sensitive: bool,
+    /// Maximum number of typos (edits) tolerated per literal term.
+    pub typo_tolerance: usize,
+    /// The fields searched by terms without a field prefix.
+    pub fields: Box<[Field]>,
+}
+
+/// A parsed search query.
+///
+/// The search text is split on whitespace into terms, all of which must match for an emoji to be
+/// part of the results. A term prefixed with `!` or `-` is negated, i.e. emojis matching it are
+/// filtered out instead. A term can be restricted to a single field with a `<field>:` prefix, e.g.
+/// `code:zap` or `!desc:performance`. A `U+XXXX` or `0xXXXX` term matches the emojis containing
+/// that Unicode codepoint, unless it's restricted to another field than the entity.
+///
+/// Terms are matched according to the [`MatchMode`], along with their synonyms unless they're
+/// regular expressions. Matching is insensitive to diacritics and Unicode normalization forms, as
+/// well as to case unless the search is case-sensitive. If nothing matches, non-regex terms are
+/// retried with some typos tolerated.
+pub struct Query {
+    terms: Box<[Term]>,
+    typo_tolerance: usize,
+    fields: Box<[Field]>,
+}
+
+impl Query {
+    pub fn parse(text: &str, options: &SearchOptions) -> Result<Self, regex::Error> {
+        Self::parse_with(text, options.mode, options)
+    }
+
+    /// Parse the query, falling back to substring matching if it's not a valid regex.
+    ///
+    /// This is meant for interactive use, where the query is very likely to be an incomplete regex
+    /// while it's being typed.
+    pub fn parse_lenient(text: &str, options: &SearchOptions) -> Self {
+        Self::parse(text, options)
+            .or_else(|_| Self::parse_with(text, MatchMode::Substring, options))
+            .expect("Escaped search text is always a valid regex")
+    }
+
+    fn parse_with(
+        text: &str,
+        mode: MatchMode,
+        options: &SearchOptions,
+    ) -> Result<Self, regex::Error> {
+        let terms = text
+            .split_whitespace()
+            .map(|term| Term::parse(term, mode, options))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            terms,
+            typo_tolerance: options.typo_tolerance,
+            fields: options.fields.clone(),
+        })
+    }
+
+    /// The emojis matching the query, in their original order.
+    ///
+    /// Typos are only tolerated if there are no exact matches.
+    pub fn filter(&self, emojis: &[&Emoji]) -> Matches {
+        let mut matches = Matches::all(emojis);
+        self.filter_all(emojis, &mut matches);
+
+        matches
+    }
+
+    /// Same as [`Query::filter`], but updating the matches of the previous query in place, and
+    /// only going through them if this query can only narrow them down, e.g. because characters
+    /// were appended to the search text.
+    pub fn refilter(&self, previous: &Query, matches: &mut Matches, emojis: &[&Emoji]) {
+        if !matches.approximate && self.refines(previous) {
+            matches
+                .indices
+                .retain(|&index| self.matches(emojis[usize::from(index)]));
+            self.tolerate_typos(emojis, matches);
+        } else {
+            self.filter_all(emojis, matches);
+        }
+    }
+
+    /// Replace the matches with the emojis matching the query.
+    fn filter_all(&self, emojis: &[&Emoji], matches: &mut Matches) {
+        matches.indices.clear();
+        matches
+            .indices
+            .extend(indices(emojis).filter(|&index| self.matches(emojis[usize::from(index)])));
+        self.tolerate_typos(emojis, matches);
+    }
+
+    /// Match the emojis with typos tolerated if none matched exactly.
+    fn tolerate_typos(&self, emojis: &[&Emoji], matches: &mut Matches) {
+        matches.approximate = matches.indices.is_empty() && self.typo_tolerance > 0;
+        if matches.approximate {
+            matches.indices.extend(
+                indices(emojis)
+                    .filter(|&index| self.matches_with_typos(emojis[usize::from(index)])),
+            );
+        }
+    }
+
+    /// Whether all the emojis matching this query also match the previous one.
+    fn refines(&self, previous: &Query) -> bool {
+        // Additional terms can only narrow down the matches further.
+        self.terms.len() >= previous.terms.len()
+            && self
+                .terms
+                .iter()
+                .zip(previous.terms.iter())
+                .all(|(term, previous)| term.refines(previous))
+    }
+
+    /// The byte ranges of the field matching the (non-negated) terms, for highlighting.
+    pub fn highlights(&self, emoji: &Emoji, field: Field) -> Vec<Range<usize>> {
+        let ranges = self
+            .terms
+            .iter()
+            .filter(|term| !term.negated && term.fields(&self.fields).contains(&field))
+            .flat_map(|term| emoji.match_ranges(field, &*term.matcher, term.case_sensitive))
+            .collect();
+
+        merge_ranges(ranges)
+    }
+
+    pub fn matches(&self, emoji: &Emoji) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.matches(emoji, &self.fields) != term.negated)
+    }
+
+    fn matches_with_typos(&self, emoji: &Emoji) -> bool {
+        self.terms.iter().all(|term| {
+            if term.negated {
+                // Only exclude what the user explicitly asked to exclude.
+                !term.matches(emoji, &self.fields)
+            } else {
+                term.matches(emoji, &self.fields)
+                    || term.matches_with_typos(emoji, &self.fields, self.typo_tolerance)
+            }
+        })
+    }
+}
+
+/// Emojis matching a [`Query`], as their indices in the emojis that were filtered.
+///
+/// Kept as indices so that they can be updated in place as the query changes, without allocating.
+pub struct Matches {
+    pub indices: Vec<u16>,
+    /// Whether typos had to be tolerated for these to match.
+    pub approximate: bool,
+}
+
+impl Matches {
+    /// All of the emojis, as matched by an empty query.
+    pub fn all(emojis: &[&Emoji]) -> Self {
+        Self {
+            indices: indices(emojis).collect(),
+            approximate: false,
+        }
+    }
+
+    /// Reset to all of the emojis, keeping the allocation.
+    pub fn reset(&mut self, emojis: &[&Emoji]) {
+        self.indices.clear();
+        self.indices.extend(indices(emojis));
+        self.approximate = false;
+    }
+
+    /// The matching emojis, out of the emojis that were filtered.
+    pub fn emojis<'m, 'e>(
+        &'m self,
+        emojis: &'m [&'e Emoji],
+    ) -> impl ExactSizeIterator<Item = &'e Emoji> + 'm {
+        self.indices.iter().map(|&index| emojis[usize::from(index)])
+    }
+}
+
+/// The indices of the emojis, of which there are at most [`MAX_EMOJIS`].
+fn indices(emojis: &[&Emoji]) -> impl Iterator<Item = u16> {
+    let len = u16::try_from(emojis.len()).expect("Emoji sets are checked to fit in a `u16`");
+
+    0..len
+}
+
+/// The maximum number of emojis of a set, for their indices to fit in [`Matches`].
+pub const MAX_EMOJIS: usize = u16::MAX as usize;
+
+struct Term {
+    matcher: Box<dyn Matcher>,
+    mode: MatchMode,
+    /// The (normalized) text the matcher was built from.
+    source: String,
+    field: Option<Field>,
+    negated: bool,
+    /// The normalized text of non-regex terms, lowercase unless the term is case-sensitive.
+    literal: Option<String>,
+    case_sensitive: bool,
+    /// Whether the term was expanded to some synonyms.
+    expanded: bool,
+}
+
+impl Term {
+    fn parse(text: &str, mode: MatchMode, options: &SearchOptions) -> Result<Self, regex::Error> {
+        let case_sensitive = options.case_sensitive;
+        // A lone `!` or `-` is searched for literally.
+        let (negated, text) = match text.strip_prefix(NEGATION_PREFIXES) {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, text),
+        };
+
+        // Unknown prefixes are part of the pattern, so that e.g. `:zap:` keeps working.
+        let (field, text) = match text.split_once(':') {
+            Some((prefix, rest)) if !rest.is_empty() => match Field::from_prefix(prefix) {
+                Some(field) => (Some(field), rest),
+                None => (None, text),
+            },
+            _ => (None, text),
+        };
+
+        // Codepoints are searched in the entity, unless the term is restricted to another field.
+        let codepoint = match field {
+            None | Some(Field::Entity) => parse_codepoint(text),
+            Some(_) => None,
+        };
+        if let Some(codepoint) = codepoint {
+            // Match the codepoint exactly, as one of the `-`-separated hex values of the entity.
+            let source = format!("(?i)[x-]0*{codepoint}[-;]");
+            let matcher = Regex::new(&source).expect("Hex digits always form a valid regex");
+
+            return Ok(Self {
+                matcher: Box::new(matcher),
+                mode: MatchMode::Regex,
+                source,
+                field: Some(Field::Entity),
+                negated,
+                literal: None,
+                expanded: false,
+                case_sensitive: false,
+            });
+        }
+
+        let text = normalize(text);
+        let (matcher, source, literal, expanded): (Box<dyn Matcher>, _, _, _) = match mode {
+            MatchMode::Regex => {
+                // The searched fields are lowercase already, unless the search is case-sensitive,
+                // but lowercasing a regex could change its meaning (e.g `\W`), so let the regex
+                // engine deal with the case of the pattern.
+                let regex = RegexBuilder::new(&text)
+                    .case_insensitive(!case_sensitive)
+                    .build()?;
+
+                (Box::new(regex), text.into_owned(), None, false)
+            }
+            MatchMode::Substring | MatchMode::Fuzzy => {
+                let text = if case_sensitive {
+                    text.into_owned()
+                } else {
+                    text.to_lowercase()
+                };
+                let words = options.synonyms.expand(&text);
+                let expanded = words.len() > 1;
+                let words = words.into_iter();
+                let matcher: Box<dyn Matcher> = if mode == MatchMode::Substring {
+                    Box::new(words.map(Substring::new).collect::<Vec<_>>())
+                } else {
+                    Box::new(words.map(|word| Fuzzy(word.to_owned())).collect::<Vec<_>>())
+                };
+
+                (matcher, text.clone(), Some(text), expanded)
+            }
+        };
+
+        Ok(Self {
+            matcher,
+            mode,
+            source,
+            field,
+            negated,
+            literal,
+            expanded,
+            case_sensitive,
+        })
+    }
+
+    /// Whether all the emojis matching this term also match the previous one.
+    fn refines(&self, previous: &Term) -> bool {
+        if self.field != previous.field || self.negated != previous.negated {
+            return false;
+        }
+        if self.mode != previous.mode || self.case_sensitive != previous.case_sensitive {
+            return false;
+        }
+        if self.source == previous.source {
+            return true;
+        }
+
+        // A field containing some text also contains any part of it, and the same goes for
+        // subsequences with fuzzy matching. Negated terms work the other way around though, and
+        // synonyms could match without containing the previous text at all.
+        match (&self.literal, &previous.literal) {
+            (Some(literal), Some(previous)) if !self.negated && !self.expanded => match self.mode {
+                MatchMode::Substring => literal.contains(previous.as_str()),
+                MatchMode::Fuzzy => Fuzzy(previous.clone()).is_match(literal),
+                MatchMode::Regex => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The fields searched by the term: the one of its prefix if any, the given ones otherwise.
+    fn fields<'f>(&'f self, fields: &'f [Field]) -> &'f [Field] {
+        match &self.field {
+            Some(field) => slice::from_ref(field),
+            None => fields,
+        }
+    }
+
+    fn matches(&self, emoji: &Emoji, fields: &[Field]) -> bool {
+        emoji.contains(self.fields(fields), &*self.matcher, self.case_sensitive)
+    }
+
+    /// Whether any word of the searched field(s) is within `tolerance` edits of the term.
+    ///
+    /// The tolerance is capped to a quarter of the term's length, so that short terms don't end up
+    /// matching pretty much anything.
+    fn matches_with_typos(&self, emoji: &Emoji, fields: &[Field], tolerance: usize) -> bool {
+        let Some(literal) = &self.literal else {
+            return false;
+        };
+        let literal: Vec<char> = literal.chars().collect();
+        let tolerance = tolerance.min(literal.len() / 4);
+        if tolerance == 0 {
+            return false;
+        }
+
+        self.fields(fields).iter().any(|field| {
+            emoji
+                .searched_texts(*field, self.case_sensitive)
+                .any(|text| {
+                    text.split(|c: char| !c.is_alphanumeric()).any(|word| {
+                        let word: Vec<char> = word.chars().collect();
+                        // Also compare against the start of the word, for partially typed terms.
+                        let prefix = &word[..word.len().min(literal.len())];
+
+                        edit_distance(&literal, &word) <= tolerance
+                            || edit_distance(&literal, prefix) <= tolerance
+                    })
+                })
+        })
+    }
+}
+
+/// The optimal string alignment distance between `a` and `b`.
+///
+/// This is the Levenshtein distance with transpositions of adjacent characters counting as a single
+/// edit, since swapped letters are one of the most common typos.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // Three rows of the usual dynamic programming matrix is all we need.
+    let mut before_prev: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (prev[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_prev[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut current);
+    }
+
+    prev[b.len()]
+}
+
+/// Parse a `U+XXXX` or `0xXXXX` codepoint into its (uppercase) hex digits.
+///
+/// Bare hex digits aren't codepoints, so that e.g. `2024` is still searched as text, which finds
+/// the emojis with that codepoint in their entity anyway.
+fn parse_codepoint(text: &str) -> Option<String> {
+    let hex = ["U+", "u+", "0x", "0X"]
+        .into_iter()
+        .find_map(|prefix| text.strip_prefix(prefix))?
+        .trim_start_matches('0');
+
+    let is_codepoint = (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+
+    is_codepoint.then(|| hex.to_ascii_uppercase())
+}
+
+const NEGATION_PREFIXES: [char; 2] = ['!', '-'];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emoji::{EMOJIS, GITMOJI};
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            mode: MatchMode::Substring,
+            synonyms: Synonyms::builtin(),
+            case_sensitive: false,
+            typo_tolerance: 1,
+            fields: Field::ALL.into(),
+        }
+    }
+
+    /// The emojis of gitmoji matching the query.
+    fn search(text: &str, options: &SearchOptions) -> Vec<&'static str> {
+        let emojis: Vec<_> = GITMOJI.emojis().iter().collect();
+        let query = Query::parse(text, options).unwrap();
+        query
+            .filter(&emojis)
+            .emojis(&emojis)
+            .map(|emoji| emoji.emoji)
+            .collect()
+    }
+
+    #[test]
+    fn negated_terms() {
+        let options = options();
+        let removals = search("remove", &options);
+        assert!(removals.contains(&"🔥"));
+        assert!(removals.contains(&"➖"));
+
+        let without_fire = search("remove !fire", &options);
+        assert!(!without_fire.contains(&"🔥"));
+        assert!(without_fire.contains(&"➖"));
+        assert_eq!(search("remove -fire", &options), without_fire);
+        // A lone negation prefix is searched for literally.
+        assert!(search("!", &options).is_empty());
+    }
+
+    #[test]
+    fn field_prefixes() {
+        let options = options();
+        assert_eq!(search("code:zap", &options), ["⚡️"]);
+        assert_eq!(search("name:sparkles", &options), ["✨"]);
+        assert_eq!(search("emoji:🐛", &options), ["🐛"]);
+        assert!(search("desc:performance", &options).contains(&"⚡️"));
+        assert!(search("zap !desc:performance", &options).is_empty());
+        // Unknown prefixes are part of the term.
+        assert_eq!(search(":zap:", &options), ["⚡️"]);
+    }
+
+    #[test]
+    fn categories() {
+        let options = options();
+        assert_eq!(search("cat:deps", &options), ["⬇️", "⬆️", "➖", "➕", "📌"]);
+        assert_eq!(search("cat:ci fix", &options), ["💚"]);
+
+        let emojis: Vec<_> = EMOJIS.emojis().iter().collect();
+        let query = Query::parse("cat:smileys-emotion grinning", &options).unwrap();
+        let found: Vec<_> = query.filter(&emojis).emojis(&emojis).collect();
+        assert!(!found.is_empty());
+        assert!(found
+            .iter()
+            .all(|emoji| emoji.category == "smileys-emotion"));
+    }
+
+    #[test]
+    fn default_fields() {
+        let options = SearchOptions {
+            fields: [Field::Code].into(),
+            ..options()
+        };
+        assert_eq!(search("zap", &options), ["⚡️"]);
+        assert!(search("performance", &options).is_empty());
+        assert!(search("desc:performance", &options).contains(&"⚡️"));
+    }
+
+    #[test]
+    fn synonyms() {
+        let options = options();
+        // 🗑️ is the wastebasket, whose description doesn't mention deleting.
+        assert!(search("delete", &options).contains(&"🗑️"));
+        assert!(search("hotfix", &options).contains(&"🚑️"));
+        assert!(search("launch", &options).is_empty());
+
+        let mut synonyms = Synonyms::builtin();
+        synonyms.add(["launch", " Rocket ", ""]);
+        let options = SearchOptions {
+            synonyms,
+            ..options
+        };
+        assert_eq!(search("launch", &options), ["🚀"]);
+
+        // Regular expressions aren't expanded.
+        let options = SearchOptions {
+            mode: MatchMode::Regex,
+            ..options
+        };
+        assert!(!search("^delete$", &options).contains(&"🗑️"));
+        assert!(search("^launch$", &options).is_empty());
+    }
+
+    #[test]
+    fn typo_tolerance() {
+        let options = options();
+        assert_eq!(search("sparlkes", &options), ["✨"]);
+        assert_eq!(search("sparkels", &options), ["✨"]);
+
+        let options = SearchOptions {
+            typo_tolerance: 0,
+            ..options
+        };
+        assert!(search("sparlkes", &options).is_empty());
+    }
+
+    #[test]
+    fn edit_distances() {
+        let distance = |a: &str, b: &str| {
+            let a: Vec<char> = a.chars().collect();
+            let b: Vec<char> = b.chars().collect();
+            edit_distance(&a, &b)
+        };
+        assert_eq!(distance("sparkles", "sparkles"), 0);
+        // Transpositions, deletions, substitutions and insertions.
+        assert_eq!(distance("sparkles", "sparlkes"), 1);
+        assert_eq!(distance("sparkles", "sparkle"), 1);
+        assert_eq!(distance("sparkles", "spurkles"), 1);
+        assert_eq!(distance("sparkles", "sparkless"), 1);
+        assert_eq!(distance("sparkles", "parklse"), 2);
+        assert_eq!(distance("", "zap"), 3);
+    }
+
+    #[test]
+    fn parse_codepoints() {
+        assert_eq!(parse_codepoint("U+2728").as_deref(), Some("2728"));
+        assert_eq!(parse_codepoint("u+1f600").as_deref(), Some("1F600"));
+        assert_eq!(parse_codepoint("U+fe0f").as_deref(), Some("FE0F"));
+        assert_eq!(parse_codepoint("0x1f600").as_deref(), Some("1F600"));
+        assert_eq!(parse_codepoint("U+01F600").as_deref(), Some("1F600"));
+        // Bare, empty, too long or not hex.
+        assert_eq!(parse_codepoint("2728"), None);
+        assert_eq!(parse_codepoint("U+"), None);
+        assert_eq!(parse_codepoint("U+1234567"), None);
+        assert_eq!(parse_codepoint("0x12g4"), None);
+    }
+
+    #[test]
+    fn numbers_are_text() {
+        let options = options();
+        let term = Term::parse("2024", options.mode, &options).unwrap();
+        assert_eq!(term.field, None);
+        assert_eq!(term.literal.as_deref(), Some("2024"));
+
+        let term = Term::parse("desc:1234", options.mode, &options).unwrap();
+        assert_eq!(term.field, Some(Field::Description));
+        assert_eq!(term.literal.as_deref(), Some("1234"));
+
+        // Even explicit codepoints, when restricted to another field than the entity.
+        let term = Term::parse("desc:U+2728", options.mode, &options).unwrap();
+        assert_eq!(term.field, Some(Field::Description));
+        assert_eq!(term.literal.as_deref(), Some("u+2728"));
+    }
+
+    #[test]
+    fn search_codepoints() {
+        let options = options();
+        assert_eq!(search("U+2728", &options), ["✨"]);
+        assert_eq!(search("entity:U+2728", &options), ["✨"]);
+        assert_eq!(search("0x1f525", &options), ["🔥"]);
+        // Through the entity.
+        assert_eq!(search("2728", &options), ["✨"]);
+    }
+}
@@ -0,0 +1,110 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    emoji::{Emoji, EmojiFormat},
+    git,
+    hook::{self, Position},
+    Error,
+};
+
+/// A commit of the branch being rebased.
+pub struct Commit {
+    /// The abbreviated hash of the commit.
+    pub hash: String,
+    pub subject: String,
+    /// Whether the subject already has an emoji, in which case the commit is left alone.
+    pub has_emoji: bool,
+}
+
+/// The revision the range of commits is to be rebased onto, e.g. `main` for `main..` or
+/// `main..HEAD`. Only ranges ending at `HEAD` can be rebased.
+pub fn onto(range: &str) -> anyhow::Result<&str> {
+    let onto = range
+        .strip_suffix("..HEAD")
+        .or_else(|| range.strip_suffix(".."))
+        .unwrap_or(range);
+    if onto.is_empty() || onto.contains("..") {
+        bail!("Only ranges ending at `HEAD` can be rebased, e.g. `main..`, not `{range}`");
+    }
+
+    Ok(onto)
+}
+
+/// The commits a rebase of `HEAD` onto the given revision goes through, oldest first.
+///
+/// Fails with [`Error::NotAGitRepo`] outside of a repository.
+pub fn commits(onto: &str, emojis: &[&'static Emoji]) -> anyhow::Result<Vec<Commit>> {
+    if git::repo_root().is_none() {
+        return Err(Error::NotAGitRepo.into());
+    }
+
+    Ok(git::rebased_subjects(onto)
+        .into_iter()
+        .map(|(hash, subject)| Commit {
+            has_emoji: hook::find_emoji(&subject, emojis).is_some(),
+            hash,
+            subject,
+        })
+        .collect())
+}
+
+/// The todo list of `git rebase -i` rewording the commits given an emoji, with their new subject
+/// after their hash where git would write the current one, and picking the others.
+pub fn todo(
+    commits: &[(&Commit, Option<&'static Emoji>)],
+    format: &EmojiFormat,
+    position: Position,
+) -> String {
+    commits
+        .iter()
+        .map(|(commit, emoji)| match emoji {
+            Some(emoji) => {
+                let subject = hook::insert_emoji(&commit.subject, &format.format(emoji), position);
+                format!("reword {} {subject}\n", commit.hash)
+            }
+            None => format!("pick {} {}\n", commit.hash, commit.subject),
+        })
+        .collect()
+}
+
+/// Rebase `HEAD` onto the revision with the todo list, the subjects of the reworded commits being
+/// the ones in the list.
+///
+/// git runs [`REWORD_EDITOR`] as the editor of their messages, which is to call [`reword`].
+pub fn start(onto: &str, todo: &str) -> anyhow::Result<()> {
+    let git_dir = git::git_dir().ok_or(Error::NotAGitRepo)?;
+    let path = git_dir.join(TODO_FILE);
+    fs::write(&path, todo).with_context(|| format!("Failed to write `{}`", path.display()))?;
+    let rebased = git::rebase_with_todo(onto, &path, REWORD_EDITOR);
+    // Copied by git already.
+    let _ = fs::remove_file(&path);
+
+    rebased
+}
+
+/// Replace the subject of the message of the commit being reworded by the rebase with the one in
+/// its todo list, or open the user's editor for the commits that aren't reworded from gimoji's todo
+/// list.
+pub fn reword(msg_file: &Path) -> anyhow::Result<()> {
+    let git_dir = git::git_dir().ok_or(Error::NotAGitRepo)?;
+    let done = fs::read_to_string(git_dir.join(DONE_FILE)).unwrap_or_default();
+    // The command being run is the last one done.
+    let subject = done
+        .lines()
+        .last()
+        .and_then(|line| line.strip_prefix("reword "))
+        .and_then(|line| line.split_once(' '))
+        .map(|(_, subject)| subject);
+
+    match subject {
+        Some(subject) => hook::replace_first_line(msg_file, subject),
+        None => hook::run_editor(msg_file),
+    }
+}
+
+/// The command git runs as the editor of the messages of the commits to reword.
+pub const REWORD_EDITOR: &str = "gimoji reword";
+const TODO_FILE: &str = "gimoji-rebase-todo";
+const DONE_FILE: &str = "rebase-merge/done";
@@ -0,0 +1,32 @@
+/// Whether the text contains any retired gitmoji code.
+pub fn contains_retired_code(text: &str) -> bool {
+    REMAPS.iter().any(|(retired, _)| text.contains(retired))
+}
+
+/// The text with the retired gitmoji codes replaced by their current equivalents, if it has any.
+pub fn migrate(text: &str) -> Option<String> {
+    if !contains_retired_code(text) {
+        return None;
+    }
+
+    let migrated = REMAPS
+        .iter()
+        .fold(text.to_owned(), |text, (retired, current)| {
+            text.replace(retired, current)
+        });
+
+    Some(migrated)
+}
+
+/// Codes gitmoji retired or renamed, and the ones replacing them.
+const REMAPS: [(&str, &str); 8] = [
+    (":pencil:", ":memo:"),
+    (":shirt:", ":rotating_light:"),
+    (":hankey:", ":poop:"),
+    // Platform specific fixes were all merged into bug fixes.
+    (":penguin:", ":bug:"),
+    (":apple:", ":bug:"),
+    (":checkered_flag:", ":bug:"),
+    (":robot:", ":bug:"),
+    (":green_apple:", ":bug:"),
+];
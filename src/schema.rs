@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt};
+
+use serde_json::{Map, Value};
+
+/// A problem found in an emoji database file.
+pub struct Problem {
+    /// The JSON pointer of the faulty value, e.g. `/gitmojis/3/code`.
+    pointer: String,
+    reason: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+
+        write!(f, "{pointer}: {}", self.reason)
+    }
+}
+
+/// Check an emoji database file against the gitmoji schema, along with gimoji's extensions.
+///
+/// Unlike deserialization, which stops at the first problem, this reports all of them.
+pub fn validate(database: &Value) -> Vec<Problem> {
+    let mut validator = Validator::default();
+    let Some(database) = validator.object(database, "") else {
+        return validator.problems;
+    };
+
+    if let Some(gitmojis) = database.get("gitmojis") {
+        validator.emojis(gitmojis, "/gitmojis");
+    }
+    if let Some(replace) = database.get("replace") {
+        if !replace.is_boolean() {
+            validator.problem("/replace", "expected `true` or `false`");
+        }
+    }
+    if let Some(hide) = database.get("hide") {
+        validator.strings(hide, "/hide");
+    }
+    if let Some(tags) = database.get("tags") {
+        if let Some(tags) = validator.object(tags, "/tags") {
+            for (code, tags) in tags {
+                validator.strings(tags, &format!("/tags/{}", escape(code)));
+            }
+        }
+    }
+    if let Some(weights) = database.get("weights") {
+        if let Some(weights) = validator.object(weights, "/weights") {
+            for (code, weight) in weights {
+                validator.weight(weight, &format!("/weights/{}", escape(code)));
+            }
+        }
+    }
+
+    validator.problems
+}
+
+#[derive(Default)]
+struct Validator {
+    problems: Vec<Problem>,
+}
+
+impl Validator {
+    fn problem(&mut self, pointer: &str, reason: impl Into<String>) {
+        self.problems.push(Problem {
+            pointer: pointer.to_owned(),
+            reason: reason.into(),
+        });
+    }
+
+    fn object<'v>(&mut self, value: &'v Value, pointer: &str) -> Option<&'v Map<String, Value>> {
+        let object = value.as_object();
+        if object.is_none() {
+            self.problem(pointer, "expected an object");
+        }
+
+        object
+    }
+
+    fn strings(&mut self, value: &Value, pointer: &str) {
+        let Some(values) = value.as_array() else {
+            return self.problem(pointer, "expected an array of strings");
+        };
+
+        for (i, value) in values.iter().enumerate() {
+            if !value.is_string() {
+                self.problem(&format!("{pointer}/{i}"), "expected a string");
+            }
+        }
+    }
+
+    fn weight(&mut self, value: &Value, pointer: &str) {
+        let is_weight = value
+            .as_i64()
+            .is_some_and(|weight| i32::try_from(weight).is_ok());
+        if !is_weight {
+            self.problem(pointer, "expected a whole number");
+        }
+    }
+
+    fn emojis(&mut self, value: &Value, pointer: &str) {
+        let Some(emojis) = value.as_array() else {
+            return self.problem(pointer, "expected an array of emojis");
+        };
+
+        // Where each code was first seen.
+        let mut codes = HashMap::new();
+        for (i, emoji) in emojis.iter().enumerate() {
+            let pointer = format!("{pointer}/{i}");
+            let Some(emoji) = self.object(emoji, &pointer) else {
+                continue;
+            };
+
+            for field in REQUIRED_FIELDS {
+                let field_pointer = format!("{pointer}/{field}");
+                match emoji.get(field) {
+                    None => self.problem(&pointer, format!("missing field `{field}`")),
+                    Some(Value::String(text)) if text.trim().is_empty() => {
+                        self.problem(&field_pointer, "must not be empty")
+                    }
+                    Some(Value::String(_)) => {}
+                    Some(_) => self.problem(&field_pointer, "expected a string"),
+                }
+            }
+            for field in OPTIONAL_FIELDS {
+                if emoji.get(field).is_some_and(|value| !value.is_string()) {
+                    self.problem(&format!("{pointer}/{field}"), "expected a string");
+                }
+            }
+            if let Some(tags) = emoji.get("tags") {
+                self.strings(tags, &format!("{pointer}/tags"));
+            }
+            match emoji.get("semver") {
+                None | Some(Value::Null) => {}
+                Some(Value::String(semver)) if SEMVER_VALUES.contains(&semver.as_str()) => {}
+                Some(_) => self.problem(
+                    &format!("{pointer}/semver"),
+                    "expected `\"major\"`, `\"minor\"`, `\"patch\"` or `null`",
+                ),
+            }
+            if let Some(weight) = emoji.get("weight") {
+                self.weight(weight, &format!("{pointer}/weight"));
+            }
+
+            if let Some(Value::String(text)) = emoji.get("emoji") {
+                if !text.trim().is_empty() && !is_emoji(text) && !is_snippet(text) {
+                    self.problem(
+                        &format!("{pointer}/emoji"),
+                        format!("`{text}` is neither an emoji nor a bracketed tag like `[WIP]`"),
+                    );
+                }
+            }
+            if let Some(Value::String(code)) = emoji.get("code") {
+                let code_pointer = format!("{pointer}/code");
+                match codes.get(code.as_str()) {
+                    Some(first) => self.problem(
+                        &code_pointer,
+                        format!("duplicate code `{code}`, first used at {first}"),
+                    ),
+                    None => {
+                        codes.insert(code.as_str(), code_pointer);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether the text looks like an emoji (sequence).
+///
+/// This is only a heuristic, rejecting the text that's obviously not one, e.g. plain words.
+fn is_emoji(text: &str) -> bool {
+    !text
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_ascii_alphabetic())
+        && text.chars().any(|c| u32::from(c) >= 0xA9)
+}
+
+/// Whether the text is a short, bracketed tag to use in place of an emoji, e.g. `[WIP]`.
+fn is_snippet(text: &str) -> bool {
+    let Some(tag) = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    else {
+        return false;
+    };
+
+    (1..=MAX_SNIPPET_LEN).contains(&tag.chars().count()) && !tag.chars().any(char::is_whitespace)
+}
+
+/// Escape a key for use in a JSON pointer, as per RFC 6901.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+const REQUIRED_FIELDS: [&str; 3] = ["code", "description", "emoji"];
+const OPTIONAL_FIELDS: [&str; 3] = ["entity", "name", "category"];
+const SEMVER_VALUES: [&str; 3] = ["major", "minor", "patch"];
+/// The maximum number of characters between the brackets of a snippet.
+const MAX_SNIPPET_LEN: usize = 16;
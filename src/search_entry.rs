@@ -1,4 +1,8 @@
-use crate::colors::Colors;
+use crate::{
+    colors::Colors,
+    matcher::MatchMode,
+    query::{Query, SearchOptions},
+};
 use ratatui::{
     style::{Modifier, Style},
     text::Span,
@@ -7,14 +11,23 @@ use ratatui::{
 
 pub struct SearchEntry {
     buf: String,
+    options: SearchOptions,
     colors: Colors,
+    // Whether the query changed since the last call to `changed_query`.
+    changed: bool,
+    // Whether the text is selected, to be replaced by what's typed next.
+    selected: bool,
 }
 
 impl SearchEntry {
-    pub fn new(colors: Colors) -> Self {
+    /// The entry, with the given text selected, e.g. the last search.
+    pub fn new(colors: Colors, options: SearchOptions, text: String) -> Self {
         Self {
-            buf: String::new(),
+            selected: !text.is_empty(),
+            buf: text,
+            options,
             colors,
+            changed: true,
         }
     }
 
@@ -22,16 +35,40 @@ impl SearchEntry {
         &self.buf
     }
 
+    /// The query, if it changed since the last call.
+    pub fn changed_query(&mut self) -> Option<Query> {
+        if !self.changed {
+            return None;
+        }
+        self.changed = false;
+
+        Some(Query::parse_lenient(&self.buf, &self.options))
+    }
+
     pub fn push(&mut self, c: char) {
+        if self.selected {
+            self.clear();
+        }
         self.buf.push(c);
+        self.changed = true;
     }
 
     pub fn pop(&mut self) {
-        self.buf.pop();
+        if self.selected {
+            return self.clear();
+        }
+        self.changed |= self.buf.pop().is_some();
     }
 
     pub fn clear(&mut self) {
+        self.changed |= !self.buf.is_empty();
         self.buf.clear();
+        self.selected = false;
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.options.mode = self.options.mode.next();
+        self.changed = true;
     }
 }
 
@@ -39,13 +76,23 @@ impl Widget for &SearchEntry {
     fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
         let (text, style) = if self.buf.is_empty() {
             (DEFAULT_TEXT, Style::default().add_modifier(Modifier::DIM))
+        } else if self.selected {
+            (
+                self.text(),
+                Style::default().add_modifier(Modifier::REVERSED),
+            )
         } else {
             (self.text(), Style::default())
         };
 
+        let title = match self.options.mode {
+            MatchMode::Substring => TITLE.to_owned(),
+            mode => format!("{TITLE} ({mode})"),
+        };
+
         let paragraph = Paragraph::new(Span::styled(text, style)).block(
             Block::default()
-                .title(TITLE)
+                .title(title)
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(self.colors.border))
                 .padding(Padding::uniform(1)),
@@ -1,41 +1,69 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, ops::Range};
 
+use crate::{
+    colors::Colors,
+    database::EmojiSet,
+    emoji::{Emoji, Field},
+    query::{Matches, Query},
+};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Rect},
     style::{Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, Padding, Row, StatefulWidget, Table, TableState, Widget},
 };
-use regex::RegexBuilder;
-
-use crate::{
-    colors::Colors,
-    emoji::{Emoji, EMOJIS},
-};
 
 pub struct SelectionView {
     state: TableState,
     colors: Colors,
+    // All the sets, with their emojis in the order they're listed in.
+    sets: Box<[EmojiSet]>,
+    // The index of the set the emojis are listed from.
+    set: usize,
+    query: Option<Query>,
+    // The emojis matching the current query, only updated when the query changes.
+    matches: Matches,
+    // The list of the matches, only rebuilt along with them rather than on every frame.
+    table: Table<'static>,
 }
 
 impl SelectionView {
-    pub fn new(colors: Colors) -> Self {
-        Self {
+    pub fn new(colors: Colors, sets: Box<[EmojiSet]>, set: usize) -> Self {
+        let mut view = Self {
             state: TableState::default().with_selected(Some(0)),
             colors,
-        }
+            query: None,
+            matches: Matches::all(sets[set].emojis()),
+            sets,
+            set,
+            table: Table::default(),
+        };
+        view.update_table();
+
+        view
     }
 
-    pub fn filtered_view(&mut self, search_text: &str) -> FilteredView {
-        let pattern = RegexBuilder::new(search_text)
-            .case_insensitive(true)
-            .build()
-            .expect("Invalid characters in search text");
+    /// Switch to listing the emojis of the next set, keeping the current query.
+    pub fn cycle_set(&mut self) {
+        self.set = (self.set + 1) % self.sets.len();
+        self.matches.reset(self.sets[self.set].emojis());
+        self.state.select(Some(0));
+        match self.query.take() {
+            Some(query) => self.set_query(query),
+            None => self.update_table(),
+        }
+    }
 
-        let emojis: Box<[&Emoji]> = EMOJIS
-            .iter()
-            .filter(|emoji| emoji.contains(&pattern))
-            .collect();
+    pub fn set_query(&mut self, query: Query) {
+        let all_emojis = self.sets[self.set].emojis();
+        match &self.query {
+            Some(previous) => query.refilter(previous, &mut self.matches, all_emojis),
+            None => self.matches = query.filter(all_emojis),
+        }
+        self.query = Some(query);
+        self.update_table();
+        let emojis = &self.matches.indices;
 
         match self.state.selected() {
             Some(idx) => {
@@ -52,27 +80,86 @@ impl SelectionView {
                 }
             }
         }
+    }
 
+    pub fn filtered_view(&mut self) -> FilteredView<'_> {
         FilteredView {
-            emojis,
+            all_emojis: self.sets[self.set].emojis(),
+            matches: &self.matches,
+            table: &self.table,
             state: &mut self.state,
-            colors: self.colors,
         }
     }
+
+    fn update_table(&mut self) {
+        let set = &self.sets[self.set];
+        let query = self.query.as_ref();
+        let highlighted = |emoji: &'static Emoji, field| {
+            let ranges = query
+                .map(|query| query.highlights(emoji, field))
+                .unwrap_or_default();
+
+            highlighted_line(emoji.field(field), &ranges)
+        };
+        let rows = self.matches.emojis(set.emojis()).map(|emoji| {
+            Row::new([
+                Line::raw(emoji.emoji),
+                highlighted(emoji, Field::Code),
+                highlighted(emoji, Field::Description),
+            ])
+        });
+
+        // Wide enough for text snippets like `[WIP]`, measured on all the emojis so that it doesn't
+        // change while searching.
+        let emoji_width = set
+            .emojis()
+            .iter()
+            .map(|emoji| Line::raw(emoji.emoji).width())
+            .max()
+            .unwrap_or_default()
+            .max(2);
+        let widths = [
+            Constraint::Length(emoji_width as u16),
+            Constraint::Percentage(12),
+            Constraint::Fill(1),
+        ];
+
+        self.table = Table::new(rows, widths)
+            .block(
+                Block::default()
+                    .title(format!("{BLOCK_TITLE} ({})", set.name))
+                    .borders(Borders::ALL)
+                    .padding(Padding::new(1, 1, 1, 0)),
+            )
+            .style(Style::default().fg(self.colors.unselected))
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(self.colors.selected),
+            )
+            .highlight_symbol(HIGHLIGHT_SYMBOL)
+            .column_spacing(2);
+    }
 }
 
 pub struct FilteredView<'s> {
-    emojis: Box<[&'s Emoji]>,
+    all_emojis: &'s [&'static Emoji],
+    matches: &'s Matches,
+    table: &'s Table<'static>,
     state: &'s mut TableState,
-    colors: Colors,
 }
 
 impl FilteredView<'_> {
-    pub fn selected(&self) -> Option<&Emoji> {
+    pub fn selected(&self) -> Option<&'static Emoji> {
         self.state
             .selected()
-            .and_then(|idx| self.emojis.get(idx))
-            .copied()
+            .and_then(|idx| self.matches.indices.get(idx))
+            .map(|&index| self.all_emojis[usize::from(index)])
+    }
+
+    /// The variants of the selected emoji, among all the emojis and not only the filtered ones.
+    pub fn selected_variants(&self) -> Option<Vec<&'static Emoji>> {
+        self.selected().map(|emoji| emoji.variants(self.all_emojis))
     }
 
     pub fn move_up(&mut self) {
@@ -84,7 +171,7 @@ impl FilteredView<'_> {
             Ordering::Less => unreachable!(),
             Ordering::Equal => {
                 // At this point emojis is guaranteed to be not empty
-                *idx = self.emojis.len() - 1;
+                *idx = self.matches.indices.len() - 1;
             }
             Ordering::Greater => *idx -= 1,
         }
@@ -96,7 +183,7 @@ impl FilteredView<'_> {
         };
 
         // At this point emojis is guaranteed to be not empty
-        if *idx == self.emojis.len() - 1 {
+        if *idx == self.matches.indices.len() - 1 {
             *idx = 0;
         } else {
             *idx += 1;
@@ -106,35 +193,25 @@ impl FilteredView<'_> {
 
 impl Widget for &mut FilteredView<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let rows = self
-            .emojis
-            .iter()
-            .map(|emoji| Row::new([emoji.emoji, emoji.code, emoji.description]));
-
-        let widths = [
-            Constraint::Percentage(3),
-            Constraint::Percentage(12),
-            Constraint::Percentage(85),
-        ];
-
-        let table = Table::new(rows, widths)
-            .block(
-                Block::default()
-                    .title(BLOCK_TITLE)
-                    .borders(Borders::ALL)
-                    .padding(Padding::new(1, 1, 1, 0)),
-            )
-            .style(Style::default().fg(self.colors.unselected))
-            .highlight_style(
-                Style::default()
-                    .add_modifier(Modifier::BOLD)
-                    .fg(self.colors.selected),
-            )
-            .highlight_symbol(HIGHLIGHT_SYMBOL)
-            .column_spacing(2);
+        StatefulWidget::render(self.table, area, buf, self.state);
+    }
+}
 
-        StatefulWidget::render(table, area, buf, self.state);
+/// Split the text into spans, with the given byte ranges underlined.
+fn highlighted_line(text: &'static str, ranges: &[Range<usize>]) -> Line<'static> {
+    let mut spans = Vec::with_capacity(ranges.len() * 2 + 1);
+    let mut end = 0;
+    for range in ranges {
+        spans.push(Span::raw(&text[end..range.start]));
+        spans.push(Span::styled(
+            &text[range.clone()],
+            Style::default().add_modifier(Modifier::UNDERLINED),
+        ));
+        end = range.end;
     }
+    spans.push(Span::raw(&text[end..]));
+
+    Line::from(spans)
 }
 
 const BLOCK_TITLE: &str = "Select an emoji";
@@ -0,0 +1,234 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    clipboard::{ClipboardBackend, ClipboardSelection},
+    colors::Colors,
+    config::{Config, DEFAULT_HISTORY_MAX_ENTRIES, DEFAULT_HOOK_SOURCES, DEFAULT_TYPO_TOLERANCE},
+    database::DEFAULT_SET,
+    emoji::{EmojiFormat, Field, Order},
+    history::{Preselect, Retention},
+    hook::{Existing, MessageSource, Position, ShowMessage},
+    keys::Keymap,
+    lint::Rules,
+    locale, paths,
+    picker::Picker,
+    query::SearchOptions,
+    suggest::Suggester,
+    synonyms::Synonyms,
+    ColorScheme, OutputFormat,
+};
+
+/// The settings gimoji runs with, resolved from all the places they can be set.
+///
+/// From the highest precedence to the lowest, these are the command line options, the environment
+/// variables, the `.gimoji.toml` file of the repository, the `gimoji.*` git config, the user's
+/// `config.toml` file, gitmoji-cli's `.gitmojirc.json` file and finally the defaults.
+#[derive(Clone)]
+pub struct Settings {
+    /// The color scheme of the picker, detected from the terminal if not set.
+    pub color_scheme: Option<ColorScheme>,
+    /// The color scheme of the picker if it can't be detected.
+    pub default_color_scheme: ColorScheme,
+    pub light_colors: Colors,
+    pub dark_colors: Colors,
+    pub keys: Keymap,
+    pub set: String,
+    pub database: DatabaseSettings,
+    pub order: Order,
+    pub picker: Picker,
+    pub preselect: Option<Preselect>,
+    pub output_format: OutputFormat,
+    pub clipboard: bool,
+    pub copy_format: EmojiFormat,
+    pub clipboard_backend: ClipboardBackend,
+    pub clipboard_command: Option<String>,
+    pub clipboard_selection: ClipboardSelection,
+    pub clipboard_timeout: Option<Duration>,
+    pub tmux_buffer: bool,
+    pub hook: HookSettings,
+    pub lint: Rules,
+    pub search: SearchOptions,
+    pub history: HistorySettings,
+    pub suggest: SuggestSettings,
+}
+
+/// The emojis suggested for commits, see [`SuggestConfig`](crate::config::SuggestConfig).
+#[derive(Debug, Clone)]
+pub struct SuggestSettings {
+    /// Whether the hook's picker starts with them.
+    pub enabled: bool,
+    pub suggester: Suggester,
+}
+
+/// Where and how much of the history of selections is kept, see
+/// [`HistoryConfig`](crate::config::HistoryConfig).
+#[derive(Debug, Clone)]
+pub struct HistorySettings {
+    /// The directory of the history, if it's to be recorded.
+    pub dir: Option<PathBuf>,
+    pub retention: Retention,
+    /// Whether the last search is saved along with the history, for the picker to start with.
+    pub remember_query: bool,
+}
+
+/// The emojis to choose from, see [`DatabaseConfig`](crate::config::DatabaseConfig).
+#[derive(Clone)]
+pub struct DatabaseSettings {
+    /// The sets available in the picker, all of them if not set.
+    pub sets: Option<Vec<String>>,
+    pub files: Vec<PathBuf>,
+    pub hide: Vec<String>,
+    pub gitmojis: Vec<toml::Table>,
+    /// The language the descriptions are translated to, if it's not English.
+    pub language: Option<String>,
+}
+
+/// How the hook edits commit messages, see [`HookConfig`](crate::config::HookConfig).
+#[derive(Clone)]
+pub struct HookSettings {
+    pub migrate_codes: bool,
+    pub position: Position,
+    pub emoji_format: EmojiFormat,
+    pub sources: Vec<MessageSource>,
+    pub existing: Existing,
+    pub skip_in_ci: bool,
+    pub skip_without_tty: bool,
+    pub show_message: ShowMessage,
+}
+
+impl Settings {
+    /// Resolve the settings, with the given ones from the command line taking precedence.
+    pub fn resolve(cli: Config) -> anyhow::Result<Self> {
+        let config = Config::layers(cli)?
+            .into_iter()
+            .map(|(_, config)| config)
+            .fold(Config::default(), Config::layered);
+
+        let fields = config.search.fields.unwrap_or_default();
+        let mut synonyms = Synonyms::builtin();
+        for group in config.search.synonyms.unwrap_or_default() {
+            synonyms.add(group.iter().map(String::as_str));
+        }
+        let mut suggester = Suggester::builtin();
+        for (word, emoji) in config.suggest.branches.unwrap_or_default() {
+            suggester.add_branch_word(&word, &emoji);
+        }
+        for (pattern, emoji) in config.suggest.paths.unwrap_or_default() {
+            suggester.add_path_pattern(&pattern, &emoji);
+        }
+        for rule in config.suggest.rules.unwrap_or_default() {
+            suggester.add_rule(rule);
+        }
+        Ok(Self {
+            color_scheme: match config.detect_color_scheme {
+                // Not detected, so the default one if not set.
+                Some(false) => Some(
+                    config
+                        .color_scheme
+                        .or(config.default_color_scheme)
+                        .unwrap_or_default(),
+                ),
+                _ => config.color_scheme,
+            },
+            default_color_scheme: config.default_color_scheme.unwrap_or_default(),
+            light_colors: config.colors.light.apply(Colors::LIGHT),
+            dark_colors: config.colors.dark.apply(Colors::DARK),
+            keys: config.keys.keymap(),
+            // The first of the available sets is the default, if they're restricted.
+            set: config
+                .set
+                .or_else(|| config.database.sets.as_ref()?.first().cloned())
+                .unwrap_or_else(|| DEFAULT_SET.to_owned()),
+            database: DatabaseSettings {
+                sets: config.database.sets,
+                files: config.database.files.unwrap_or_default(),
+                hide: config.database.hide.unwrap_or_default(),
+                gitmojis: config.database.gitmojis.unwrap_or_default(),
+                language: locale::language(config.language.as_deref()),
+            },
+            order: config.order.unwrap_or_default(),
+            picker: config.picker.unwrap_or_default(),
+            preselect: config.preselect,
+            output_format: config.output_format.unwrap_or_default(),
+            clipboard: config.clipboard.unwrap_or(true),
+            copy_format: config
+                .copy_format
+                .or_else(|| config.emoji_format.clone())
+                .unwrap_or_default(),
+            clipboard_backend: config.clipboard_backend.unwrap_or_default(),
+            clipboard_command: config.clipboard_command,
+            clipboard_selection: config.clipboard_selection.unwrap_or_default(),
+            clipboard_timeout: config.clipboard_timeout.map(Duration::from_secs),
+            tmux_buffer: config.tmux_buffer.unwrap_or_default(),
+            hook: HookSettings {
+                migrate_codes: config.hook.migrate_codes.unwrap_or_default(),
+                position: config.hook.position.unwrap_or_default(),
+                emoji_format: config
+                    .hook
+                    .emoji_format
+                    .or_else(|| config.emoji_format.clone())
+                    .unwrap_or_default(),
+                sources: config
+                    .hook
+                    .sources
+                    .unwrap_or_else(|| DEFAULT_HOOK_SOURCES.into()),
+                existing: config.hook.existing.unwrap_or_default(),
+                skip_in_ci: config.hook.skip_in_ci.unwrap_or_default(),
+                skip_without_tty: config.hook.skip_without_tty.unwrap_or_default(),
+                show_message: config.hook.show_message.unwrap_or_default(),
+            },
+            lint: Rules {
+                require_emoji: config.lint.require_emoji.unwrap_or(true),
+                position: config.lint.position,
+                allowed: config.lint.allowed,
+                max_emojis: config.lint.max_emojis,
+                match_commit_type: config.lint.match_commit_type.unwrap_or_default(),
+            },
+            search: SearchOptions {
+                mode: config.search.match_mode.unwrap_or_default(),
+                synonyms,
+                case_sensitive: config.search.case_sensitive.unwrap_or_default(),
+                typo_tolerance: config
+                    .search
+                    .typo_tolerance
+                    .unwrap_or(DEFAULT_TYPO_TOLERANCE),
+                fields: if fields.is_empty() {
+                    Field::ALL.into()
+                } else {
+                    fields.into()
+                },
+            },
+            history: HistorySettings {
+                dir: match config.history.enabled {
+                    Some(false) => None,
+                    _ => config.history.dir.or_else(paths::data_dir),
+                },
+                retention: Retention {
+                    max_entries: config
+                        .history
+                        .max_entries
+                        .unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES),
+                    max_age: config
+                        .history
+                        .max_age_days
+                        .map(|days| Duration::from_secs(days * SECONDS_PER_DAY)),
+                },
+                remember_query: config.history.remember_query.unwrap_or_default(),
+            },
+            suggest: SuggestSettings {
+                enabled: config.suggest.enabled.unwrap_or(true),
+                suggester,
+            },
+        })
+    }
+
+    /// The colors of the given color scheme.
+    pub fn colors(&self, color_scheme: ColorScheme) -> Colors {
+        match color_scheme {
+            ColorScheme::Light => self.light_colors,
+            ColorScheme::Dark => self.dark_colors,
+        }
+    }
+}
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
@@ -0,0 +1,628 @@
+use std::{collections::BTreeMap, ptr};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{database::EmojiSet, emoji::Emoji, git, Error};
+
+/// What a commit is made on, which emojis are suggested from.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// The branch checked out, if any.
+    pub branch: Option<String>,
+    /// The staged paths, relative to the root of the repository.
+    pub paths: Vec<String>,
+    /// The lines added or removed by the staged changes, without their `+` or `-`.
+    pub diff: Vec<String>,
+}
+
+impl Changes {
+    /// The changes staged in the current repository, on the branch checked out. The diff, which can
+    /// be huge, is only read if asked for.
+    ///
+    /// Fails with [`Error::NotAGitRepo`] outside of a repository.
+    pub fn staged(with_diff: bool) -> anyhow::Result<Self> {
+        if git::repo_root().is_none() {
+            return Err(Error::NotAGitRepo.into());
+        }
+        let diff = match with_diff {
+            true => git::staged_diff().unwrap_or_default(),
+            false => String::new(),
+        };
+
+        Ok(Self {
+            // Not on a branch in the middle of a rebase, for example.
+            branch: git::current_branch(),
+            paths: git::staged_paths(),
+            diff: diff
+                .lines()
+                .filter(|line| !line.starts_with("+++ ") && !line.starts_with("--- "))
+                .filter_map(|line| line.strip_prefix(['+', '-']))
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+}
+
+/// A rule suggesting an emoji for the commits it matches, e.g.
+/// `{ emoji = ":lock:", diff = "password|token", weight = 3 }` in the `rules` of the `[suggest]`
+/// settings.
+///
+/// It matches the commits matching all of its patterns, and all commits if it has none.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rule {
+    /// The emoji suggested, as its code or itself.
+    pub emoji: String,
+    /// How much the rule counts, the emojis being suggested by decreasing total weight of the rules
+    /// matching the commit. Negative to keep an emoji from being suggested.
+    #[serde(default = "default_weight")]
+    pub weight: i32,
+    /// A case-insensitive regex found in the branch.
+    pub branch: Option<Pattern>,
+    /// A glob pattern matching some of the staged paths.
+    pub paths: Option<Glob>,
+    /// A glob pattern matching all the staged paths, of which there's at least one.
+    pub only_paths: Option<Glob>,
+    /// A case-insensitive regex found in a line added or removed by the staged changes.
+    pub diff: Option<Pattern>,
+}
+
+impl Rule {
+    fn new(emoji: &str, weight: i32) -> Self {
+        Self {
+            emoji: emoji.to_owned(),
+            weight,
+            branch: None,
+            paths: None,
+            only_paths: None,
+            diff: None,
+        }
+    }
+
+    /// Where the rule matches in the branch if it matches the changes, for the emojis of earlier
+    /// words of the branch to come first. 0 if it has no pattern for the branch.
+    fn find(&self, changes: &Changes) -> Option<usize> {
+        let position = match &self.branch {
+            Some(Pattern(regex)) => regex.find(changes.branch.as_deref()?)?.start(),
+            None => 0,
+        };
+        let matches_paths = self
+            .paths
+            .as_ref()
+            .is_none_or(|glob| changes.paths.iter().any(|path| glob.regex.is_match(path)));
+        let matches_only_paths = self.only_paths.as_ref().is_none_or(|glob| {
+            !changes.paths.is_empty() && changes.paths.iter().all(|path| glob.regex.is_match(path))
+        });
+        let matches_diff = self
+            .diff
+            .as_ref()
+            .is_none_or(|Pattern(regex)| changes.diff.iter().any(|line| regex.is_match(line)));
+
+        (matches_paths && matches_only_paths && matches_diff).then_some(position)
+    }
+}
+
+/// A case-insensitive regex of a [`Rule`].
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        RegexBuilder::new(&source)
+            .case_insensitive(true)
+            .build()
+            .map(Self)
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> Self {
+        pattern.0.as_str().to_owned()
+    }
+}
+
+/// A glob pattern of paths of a [`Rule`].
+///
+/// `*` matches anything but `/`, `**` anything, and `?` any character but `/`. Patterns without a
+/// `/` match the file names, in any directory, like in `.gitignore`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(from = "String", into = "String")]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl From<String> for Glob {
+    fn from(pattern: String) -> Self {
+        let mut source = String::from(if pattern.contains('/') {
+            "^"
+        } else {
+            "(?:^|/)"
+        });
+        let mut chars = pattern.trim_start_matches('/').chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `dir/**/file` also matches `dir/file`.
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        source.push_str("(?:.*/)?");
+                    } else {
+                        source.push_str(".*");
+                    }
+                }
+                '*' => source.push_str("[^/]*"),
+                '?' => source.push_str("[^/]"),
+                c => source.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        source.push('$');
+
+        Self {
+            regex: Regex::new(&source).expect("Escaped globs always form a valid regex"),
+            pattern,
+        }
+    }
+}
+
+impl From<Glob> for String {
+    fn from(glob: Glob) -> Self {
+        glob.pattern
+    }
+}
+
+/// An emoji suggested for a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The emoji, as its code or itself.
+    pub emoji: String,
+    /// The total weight of the rules suggesting it.
+    pub score: i32,
+}
+
+/// The emojis suggested for commits from what they're made on, by the rules matching them, e.g. to
+/// come first in the hook's picker.
+///
+/// Besides the rules, branches are split into words, e.g. `feature/ABC-12-login-docs` into
+/// `feature`, `abc`, `12`, `login` and `docs`, and each word with an emoji suggests it, the earlier
+/// words first, so that the prefixes of branch naming conventions and ticket keys both work. The
+/// staged paths suggest the emojis of the glob patterns they match, e.g. 📝 for `*.md`, more so if
+/// they all match, e.g. for a commit only changing docs.
+#[derive(Debug, Clone)]
+pub struct Suggester {
+    rules: Vec<Rule>,
+    /// The rules of the words of branches.
+    branches: BTreeMap<String, Rule>,
+    /// The rules of the glob patterns of paths, for when some of the paths match and when they all
+    /// do.
+    paths: BTreeMap<String, [Rule; 2]>,
+}
+
+impl Suggester {
+    pub fn builtin() -> Self {
+        let mut suggester = Self {
+            rules: Vec::new(),
+            branches: BTreeMap::new(),
+            paths: BTreeMap::new(),
+        };
+        for (word, emoji) in BUILTIN_BRANCHES {
+            suggester.add_branch_word(word, emoji);
+        }
+        for (pattern, emoji) in BUILTIN_PATHS {
+            suggester.add_path_pattern(pattern, emoji);
+        }
+
+        suggester
+    }
+
+    /// Add a rule, which comes before the ones of branch words and path patterns when they suggest
+    /// emojis with the same score.
+    pub fn add_rule(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Have branches with the word suggest the emoji, given as its code or itself, replacing the
+    /// built-in one if any. An empty emoji makes the word suggest nothing.
+    pub fn add_branch_word(&mut self, word: &str, emoji: &str) {
+        let word = word.trim().to_lowercase();
+        let emoji = emoji.trim();
+        if emoji.is_empty() {
+            self.branches.remove(&word);
+            return;
+        }
+
+        let source = format!(
+            "(?:^|[^[:alnum:]]){}(?:[^[:alnum:]]|$)",
+            regex::escape(&word)
+        );
+        let rule = Rule {
+            branch: Some(Pattern::try_from(source).expect("Escaped words form a valid regex")),
+            ..Rule::new(emoji, BRANCH_WORD_WEIGHT)
+        };
+        self.branches.insert(word, rule);
+    }
+
+    /// Have the paths matching the glob pattern suggest the emoji, given as its code or itself,
+    /// replacing the built-in one of the pattern if any. An empty emoji makes the pattern suggest
+    /// nothing.
+    pub fn add_path_pattern(&mut self, pattern: &str, emoji: &str) {
+        let pattern = pattern.trim();
+        let emoji = emoji.trim();
+        if emoji.is_empty() {
+            self.paths.remove(pattern);
+            return;
+        }
+
+        let glob = Glob::from(pattern.to_owned());
+        let rules = [
+            Rule {
+                paths: Some(glob.clone()),
+                ..Rule::new(emoji, SOME_PATHS_WEIGHT)
+            },
+            Rule {
+                only_paths: Some(glob),
+                ..Rule::new(emoji, ONLY_PATHS_WEIGHT)
+            },
+        ];
+        self.paths.insert(pattern.to_owned(), rules);
+    }
+
+    /// Whether the rules need the diff of the changes, which is only read if so.
+    pub fn needs_diff(&self) -> bool {
+        self.rules.iter().any(|rule| rule.diff.is_some())
+    }
+
+    /// The emojis suggested for the commit being made in the current directory, the best first.
+    /// None outside of a repository.
+    pub fn for_commit(&self) -> Vec<Suggestion> {
+        Changes::staged(self.needs_diff())
+            .map(|changes| self.suggest(&changes))
+            .unwrap_or_default()
+    }
+
+    /// The emojis suggested for a commit of the changes, the best first: the ones with the highest
+    /// scores, then the ones of the earliest words of the branch, then the ones of the earliest
+    /// rules. Emojis with a score of 0 or less aren't suggested.
+    pub fn suggest(&self, changes: &Changes) -> Vec<Suggestion> {
+        let rules = self
+            .rules
+            .iter()
+            .chain(self.branches.values())
+            .chain(self.paths.values().flatten());
+        // The emojis with their scores and the earliest position of their rules in the branch.
+        let mut scores: Vec<(Suggestion, usize)> = Vec::new();
+        for rule in rules {
+            let Some(position) = rule.find(changes) else {
+                continue;
+            };
+            match scores.iter_mut().find(|(s, _)| s.emoji == rule.emoji) {
+                Some((suggestion, earliest)) => {
+                    suggestion.score += rule.weight;
+                    *earliest = position.min(*earliest);
+                }
+                None => scores.push((
+                    Suggestion {
+                        emoji: rule.emoji.clone(),
+                        score: rule.weight,
+                    },
+                    position,
+                )),
+            }
+        }
+        // Stable, so that ties stay in the order of the rules.
+        scores.sort_by_key(|(suggestion, position)| (-suggestion.score, *position));
+
+        scores
+            .into_iter()
+            .map(|(suggestion, _)| suggestion)
+            .filter(|suggestion| suggestion.score > 0)
+            .collect()
+    }
+}
+
+impl Default for Suggester {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// The emojis of the set that are suggested, with their scores, in the order of the suggestions.
+/// Suggestions that aren't in the set are ignored.
+pub fn resolve(
+    emojis: &[&'static Emoji],
+    suggestions: &[Suggestion],
+) -> Vec<(&'static Emoji, i32)> {
+    let mut resolved: Vec<(&'static Emoji, i32)> = Vec::new();
+    for suggestion in suggestions {
+        let emoji = emojis
+            .iter()
+            .find(|emoji| emoji.code == suggestion.emoji || emoji.emoji == suggestion.emoji);
+        if let Some(&emoji) = emoji {
+            // Suggested by both its code and itself, the first suggestion being the best.
+            if !resolved.iter().any(|&(e, _)| ptr::eq(e, emoji)) {
+                resolved.push((emoji, suggestion.score));
+            }
+        }
+    }
+
+    resolved
+}
+
+/// A copy of the set with the suggested emojis first, in the order of the suggestions, the others
+/// staying in their order. Suggestions that aren't in the set are ignored.
+pub fn promoted(set: &EmojiSet, suggestions: &[Suggestion]) -> EmojiSet {
+    let suggestions = suggestions.to_vec();
+
+    set.map(move |_, all| {
+        let mut emojis: Vec<&'static Emoji> = resolve(all, &suggestions)
+            .into_iter()
+            .map(|(emoji, _)| emoji)
+            .collect();
+        let others: Vec<_> = all
+            .iter()
+            .filter(|&&emoji| !emojis.iter().any(|&e| ptr::eq(e, emoji)))
+            .collect();
+        emojis.extend(others);
+
+        emojis
+    })
+}
+
+fn default_weight() -> i32 {
+    1
+}
+
+/// The weights of the rules of branch words and path patterns, so that the emoji of a commit only
+/// changing docs comes before the one of its branch, which comes before the one of a commit
+/// changing some docs.
+const BRANCH_WORD_WEIGHT: i32 = 2;
+const SOME_PATHS_WEIGHT: i32 = 1;
+const ONLY_PATHS_WEIGHT: i32 = 2;
+
+/// The words of branch naming conventions and the gitmoji codes they suggest.
+const BUILTIN_BRANCHES: &[(&str, &str)] = &[
+    ("feat", ":sparkles:"),
+    ("feature", ":sparkles:"),
+    ("fix", ":bug:"),
+    ("bugfix", ":bug:"),
+    ("bug", ":bug:"),
+    ("hotfix", ":ambulance:"),
+    ("docs", ":memo:"),
+    ("doc", ":memo:"),
+    ("refactor", ":recycle:"),
+    ("perf", ":zap:"),
+    ("test", ":white_check_mark:"),
+    ("tests", ":white_check_mark:"),
+    ("style", ":art:"),
+    ("ui", ":lipstick:"),
+    ("ci", ":construction_worker:"),
+    ("build", ":building_construction:"),
+    ("chore", ":wrench:"),
+    ("config", ":wrench:"),
+    ("deps", ":arrow_up:"),
+    ("release", ":bookmark:"),
+    ("revert", ":rewind:"),
+    ("security", ":lock:"),
+    ("i18n", ":globe_with_meridians:"),
+    ("wip", ":construction:"),
+];
+
+/// The glob patterns of the paths of docs, CI config and lockfiles, and the gitmoji codes they
+/// suggest.
+const BUILTIN_PATHS: &[(&str, &str)] = &[
+    ("*.md", ":memo:"),
+    ("*.rst", ":memo:"),
+    ("*.adoc", ":memo:"),
+    ("docs/**", ":memo:"),
+    ("doc/**", ":memo:"),
+    (".github/workflows/**", ":construction_worker:"),
+    (".gitlab-ci.yml", ":construction_worker:"),
+    (".circleci/**", ":construction_worker:"),
+    (".travis.yml", ":construction_worker:"),
+    ("azure-pipelines.yml", ":construction_worker:"),
+    ("Jenkinsfile", ":construction_worker:"),
+    ("Cargo.lock", ":arrow_up:"),
+    ("package-lock.json", ":arrow_up:"),
+    ("yarn.lock", ":arrow_up:"),
+    ("pnpm-lock.yaml", ":arrow_up:"),
+    ("poetry.lock", ":arrow_up:"),
+    ("Pipfile.lock", ":arrow_up:"),
+    ("Gemfile.lock", ":arrow_up:"),
+    ("composer.lock", ":arrow_up:"),
+    ("go.sum", ":arrow_up:"),
+    ("flake.lock", ":arrow_up:"),
+    (".gitignore", ":see_no_evil:"),
+    ("LICENSE*", ":page_facing_up:"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emoji::GITMOJI;
+
+    fn changes(branch: &str, paths: &[&str], diff: &[&str]) -> Changes {
+        Changes {
+            branch: Some(branch.to_owned()).filter(|branch| !branch.is_empty()),
+            paths: paths.iter().map(|&path| path.to_owned()).collect(),
+            diff: diff.iter().map(|&line| line.to_owned()).collect(),
+        }
+    }
+
+    /// The emojis suggested for the changes, with their scores.
+    fn suggest(suggester: &Suggester, changes: &Changes) -> Vec<(String, i32)> {
+        suggester
+            .suggest(changes)
+            .into_iter()
+            .map(|suggestion| (suggestion.emoji, suggestion.score))
+            .collect()
+    }
+
+    fn suggested(suggestions: &[(&str, i32)]) -> Vec<(String, i32)> {
+        suggestions
+            .iter()
+            .map(|&(emoji, score)| (emoji.to_owned(), score))
+            .collect()
+    }
+
+    #[test]
+    fn branch_words() {
+        let suggester = Suggester::builtin();
+        // The earlier words of the branch first.
+        assert_eq!(
+            suggest(&suggester, &changes("feature/ABC-12-fix-docs", &[], &[])),
+            suggested(&[(":sparkles:", 2), (":bug:", 2), (":memo:", 2)])
+        );
+        assert_eq!(
+            suggest(&suggester, &changes("docs_fix", &[], &[])),
+            suggested(&[(":memo:", 2), (":bug:", 2)])
+        );
+        // Only whole words.
+        assert!(suggest(&suggester, &changes("prefix/fixture", &[], &[])).is_empty());
+        assert!(suggest(&suggester, &changes("", &[], &[])).is_empty());
+    }
+
+    #[test]
+    fn path_patterns() {
+        let suggester = Suggester::builtin();
+        // All the paths matching counts more than some of them.
+        assert_eq!(
+            suggest(
+                &suggester,
+                &changes("", &["README.md", "docs/guide.adoc"], &[])
+            ),
+            suggested(&[(":memo:", 3)])
+        );
+        assert_eq!(
+            suggest(&suggester, &changes("", &["README.md", "src/main.rs"], &[])),
+            suggested(&[(":memo:", 1)])
+        );
+        // A commit only changing docs beats its branch, which beats some docs.
+        assert_eq!(
+            suggest(&suggester, &changes("fix/typo", &["README.md"], &[])),
+            suggested(&[(":memo:", 3), (":bug:", 2)])
+        );
+        assert_eq!(
+            suggest(
+                &suggester,
+                &changes("fix/typo", &["README.md", "src/lib.rs"], &[])
+            ),
+            suggested(&[(":bug:", 2), (":memo:", 1)])
+        );
+    }
+
+    #[test]
+    fn rule_weights() {
+        let mut suggester = Suggester::builtin();
+        suggester.add_rule(Rule {
+            diff: Some(Pattern::try_from("password|token".to_owned()).unwrap()),
+            ..Rule::new(":lock:", 3)
+        });
+        suggester.add_rule(Rule {
+            paths: Some(Glob::from("*.md".to_owned())),
+            ..Rule::new(":memo:", -3)
+        });
+        assert!(suggester.needs_diff());
+
+        let changes = changes(
+            "docs/tokens",
+            &["README.md", "src/auth.rs"],
+            &["let Token = env::var(\"TOKEN\")?;"],
+        );
+        // The branch and paths suggest 📝 with a score of 3, which the negative rule cancels.
+        assert_eq!(suggest(&suggester, &changes), suggested(&[(":lock:", 3)]));
+    }
+
+    #[test]
+    fn replaced_builtins() {
+        let mut suggester = Suggester::builtin();
+        suggester.add_branch_word("fix", ":adhesive_bandage:");
+        suggester.add_branch_word("docs", "");
+        suggester.add_path_pattern("*.md", "📖");
+        assert!(!suggester.needs_diff());
+
+        assert_eq!(
+            suggest(&suggester, &changes("fix/docs", &["README.md"], &[])),
+            suggested(&[("📖", 3), (":adhesive_bandage:", 2)])
+        );
+    }
+
+    #[test]
+    fn rules_from_toml() {
+        let rule: Rule =
+            toml::from_str("emoji = \":lock:\"\nbranch = \"^SEC-\"\nonly-paths = \"src/**\"")
+                .unwrap();
+        assert_eq!(rule.weight, 1);
+        assert_eq!(
+            rule.find(&changes("sec-12", &["src/auth.rs"], &[])),
+            Some(0)
+        );
+        assert_eq!(
+            rule.find(&changes("sec-12", &["src/auth.rs", "a.md"], &[])),
+            None
+        );
+        assert_eq!(
+            rule.find(&changes("fix/sec-12", &["src/auth.rs"], &[])),
+            None
+        );
+        assert_eq!(rule.find(&changes("sec-12", &[], &[])), None);
+
+        assert!(toml::from_str::<Rule>("emoji = \":lock:\"\nbranch = \"(\"").is_err());
+        assert!(toml::from_str::<Rule>("emoji = \":lock:\"\nbrnach = \"sec\"").is_err());
+    }
+
+    #[test]
+    fn globs() {
+        let matches =
+            |pattern: &str, path: &str| Glob::from(pattern.to_owned()).regex.is_match(path);
+        // Patterns without a `/` match file names in any directory.
+        assert!(matches("*.md", "README.md"));
+        assert!(matches("*.md", "docs/guide/intro.md"));
+        assert!(!matches("*.md", "README.mdx"));
+        assert!(matches("Cargo.lock", "crates/core/Cargo.lock"));
+        // Others match from the root.
+        assert!(matches("docs/**", "docs/guide/intro.md"));
+        assert!(!matches("docs/**", "src/docs/intro.md"));
+        assert!(matches("/src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/bin/main.rs"));
+        assert!(matches("src/**/mod.rs", "src/mod.rs"));
+        assert!(matches("src/**/mod.rs", "src/a/b/mod.rs"));
+        assert!(matches("LICENSE-?", "LICENSE-A"));
+        assert!(!matches("a?b", "a/b"));
+        // Other characters are literal.
+        assert!(!matches("a.b", "axb"));
+    }
+
+    #[test]
+    fn resolved_suggestions() {
+        let emojis: Vec<_> = GITMOJI.emojis().iter().collect();
+        let suggestions = [
+            Suggestion {
+                emoji: ":bug:".to_owned(),
+                score: 3,
+            },
+            Suggestion {
+                emoji: ":unknown:".to_owned(),
+                score: 2,
+            },
+            Suggestion {
+                emoji: "🐛".to_owned(),
+                score: 2,
+            },
+            Suggestion {
+                emoji: "✨".to_owned(),
+                score: 1,
+            },
+        ];
+
+        let resolved: Vec<_> = resolve(&emojis, &suggestions)
+            .into_iter()
+            .map(|(emoji, score)| (emoji.code, score))
+            .collect();
+        assert_eq!(resolved, [(":bug:", 3), (":sparkles:", 1)]);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::normalize::normalize;
+
+/// Groups of interchangeable search words.
+///
+/// A (literal) search term equal to any word of a group also matches all the other words of that
+/// group, so that common vocabulary differences between the user and the database don't lead to
+/// empty results, e.g. `delete` finds 🗑️ (wastebasket).
+#[derive(Debug, Clone)]
+pub struct Synonyms {
+    groups: Vec<Box<[String]>>,
+}
+
+impl Synonyms {
+    /// The built-in synonyms, which the user's from the `synonyms` search setting are added to.
+    pub fn builtin() -> Self {
+        let mut synonyms = Self { groups: Vec::new() };
+        for group in BUILTIN {
+            synonyms.add(group.iter().copied());
+        }
+
+        synonyms
+    }
+
+    /// Add a group of interchangeable words.
+    ///
+    /// Groups with less than two (non-blank) words are ignored.
+    pub fn add<'w>(&mut self, words: impl IntoIterator<Item = &'w str>) {
+        let group: Box<[String]> = words
+            .into_iter()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(|word| normalize(word).to_lowercase())
+            .collect();
+
+        if group.len() > 1 {
+            self.groups.push(group);
+        }
+    }
+
+    /// All the words the given one should match, including itself.
+    pub fn expand<'s>(&'s self, word: &'s str) -> Vec<&'s str> {
+        let mut words = vec![word];
+        for group in &self.groups {
+            if group.iter().any(|w| w.eq_ignore_ascii_case(word)) {
+                words.extend(group.iter().map(String::as_str).filter(|w| *w != word));
+            }
+        }
+
+        words
+    }
+}
+
+impl Default for Synonyms {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+const BUILTIN: &[&[&str]] = &[
+    &["delete", "remove", "wastebasket"],
+    &["speed", "performance", "fast", "high voltage"],
+    &["typo", "pencil"],
+    &["feature", "sparkles"],
+    &["bug", "lady beetle"],
+    &["fix", "adhesive bandage"],
+    &["hotfix", "ambulance"],
+    &["docs", "documentation", "memo"],
+    &["deploy", "release", "rocket"],
+    &["refactor", "recycling symbol"],
+    &["style", "format", "artist palette"],
+    &["security", "locked"],
+    &["config", "configuration", "settings", "wrench", "gear"],
+    &["wip", "construction"],
+    &["dependency", "dependencies", "package"],
+    &["upgrade", "up arrow"],
+    &["downgrade", "down arrow"],
+    &["i18n", "translation", "globe with meridians"],
+    &["ci", "green heart", "construction worker"],
+    &["revert", "fast reverse button"],
+    &["init", "initial", "party popper"],
+    &["logs", "logging", "loudspeaker"],
+    &["ui", "lipstick"],
+    &["a11y", "accessibility", "wheelchair symbol"],
+    &["analytics", "chart increasing"],
+    &["idea", "light bulb"],
+    &["test", "tests", "test tube", "check mark"],
+];
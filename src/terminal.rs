@@ -1,91 +1,220 @@
 use anyhow::Context;
+use base64::prelude::{Engine, BASE64_STANDARD};
+#[cfg(feature = "tui")]
 use crossterm::{
+    cursor::{MoveUp, RestorePosition, SavePosition},
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
+#[cfg(feature = "tui")]
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Margin, Rect},
+    TerminalOptions, Viewport,
 };
-use std::io::{self, Stdout};
+use std::io::Write;
+#[cfg(feature = "tui")]
+use std::io::{self, IsTerminal};
 
 use crate::{
-    colors::Colors,
+    clipboard::ClipboardSelection, colors::Colors, database::EmojiSet, emoji::Emoji, keys::Keymap,
+    query::SearchOptions, Error,
+};
+#[cfg(feature = "tui")]
+use crate::{
+    commit_list::CommitList,
+    keys::{Action, Key, Modifiers},
+    message_pane::MessagePane,
     search_entry::SearchEntry,
     selection_view::{FilteredView, SelectionView},
+    variant_picker::VariantPicker,
 };
 
+/// Where the picker is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Screen {
+    /// The whole terminal, in its alternate screen so that what was there is back afterwards.
+    #[default]
+    Full,
+    /// The given number of lines at the bottom of the terminal, below the cursor, cleared
+    /// afterwards, e.g. in small panes.
+    Inline(u16),
+}
+
+#[cfg(feature = "tui")]
 pub struct Terminal {
-    term: ratatui::Terminal<CrosstermBackend<Stdout>>,
+    term: ratatui::Terminal<CrosstermBackend<Box<dyn Write>>>,
+    screen: Screen,
     search_entry: SearchEntry,
     selection_view: SelectionView,
+    // Open after selecting an emoji with variants, until one is picked or it's dismissed.
+    variant_picker: Option<VariantPicker>,
+    // The commits emojis are picked for, listed beside the picker, if any.
+    commit_list: Option<CommitList>,
+    // The commit message the emoji is picked for, shown above the picker, if any.
+    message_pane: Option<MessagePane>,
+    colors: Colors,
+    keys: Keymap,
+    // Whether the terminal was reset already.
+    reset: bool,
 }
 
+#[cfg(feature = "tui")]
 #[derive(Default)]
 pub enum EventResponse {
     #[default]
     Noop,
-    EmojiSelected(&'static str),
+    EmojiSelected(&'static Emoji),
     Exit,
 }
 
+#[cfg(feature = "tui")]
 impl Terminal {
-    pub fn new(colors: Colors) -> anyhow::Result<Self> {
+    pub fn new(
+        colors: Colors,
+        keys: Keymap,
+        search_options: SearchOptions,
+        sets: Box<[EmojiSet]>,
+        selected_set: usize,
+        screen: Screen,
+        query: String,
+    ) -> anyhow::Result<Self> {
+        let mut output = output()?;
         terminal::enable_raw_mode().context("Failed to enable raw mode")?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen).context("Failed to enter alternate screen")?;
-        let backend = CrosstermBackend::new(stdout);
-        let search_entry = SearchEntry::new(colors);
-        let selection_view = SelectionView::new(colors);
-        let term = ratatui::Terminal::new(backend).context("Failed to create terminal instance")?;
+        let viewport = match screen {
+            Screen::Full => {
+                execute!(output, EnterAlternateScreen)
+                    .context("Failed to enter alternate screen")?;
+                Viewport::Fullscreen
+            }
+            Screen::Inline(height) => {
+                let (columns, rows) = terminal::size().context("Failed to get terminal size")?;
+                let height = height.min(rows);
+                // Make room below the cursor, scrolling up if needed, without asking the terminal
+                // where the cursor is as its answer goes to stdout, which may be redirected.
+                output
+                    .write_all(&b"\n".repeat(height.into()))
+                    .context("Failed to make room for the picker")?;
+                execute!(output, MoveUp(height), SavePosition)
+                    .context("Failed to save cursor position")?;
+                Viewport::Fixed(Rect::new(0, rows - height, columns, height))
+            }
+        };
+        let backend = CrosstermBackend::new(output);
+        let search_entry = SearchEntry::new(colors, search_options, query);
+        let selection_view = SelectionView::new(colors, sets, selected_set);
+        let term = ratatui::Terminal::with_options(backend, TerminalOptions { viewport })
+            .context("Failed to create terminal instance")?;
         Ok(Self {
             term,
+            screen,
             search_entry,
             selection_view,
+            variant_picker: None,
+            commit_list: None,
+            message_pane: None,
+            colors,
+            keys,
+            reset: false,
         })
     }
 
     pub fn render_ui(&mut self) -> anyhow::Result<EventResponse> {
-        let mut filtered_view = self.selection_view.filtered_view(self.search_entry.text());
-
+        if let Some(query) = self.search_entry.changed_query() {
+            self.selection_view.set_query(query);
+        }
+        let mut filtered_view = self.selection_view.filtered_view();
         self.term
             .draw(|f| {
+                let mut area = f.size();
+                // The commits go on the left.
+                if let Some(commit_list) = &self.commit_list {
+                    let [commits, picker] =
+                        Layout::horizontal([Constraint::Percentage(40), Constraint::Fill(1)])
+                            .areas(area);
+                    f.render_widget(commit_list, commits.inner(Margin::new(1, 1)));
+                    area = picker;
+                }
+                let message_height = self.message_pane.as_ref().map_or(0, MessagePane::height);
                 let chunks = Layout::default()
-                    .constraints([Constraint::Min(5), Constraint::Percentage(100)].as_ref())
+                    .constraints(
+                        [
+                            Constraint::Length(message_height),
+                            Constraint::Min(5),
+                            Constraint::Percentage(100),
+                        ]
+                        .as_ref(),
+                    )
                     .margin(1)
-                    .split(f.size());
+                    .split(area);
+
+                // The commit message goes at the very top.
+                if let Some(message_pane) = &self.message_pane {
+                    f.render_widget(message_pane, chunks[0]);
+                }
 
                 // The search entry goes at the top.
-                f.render_widget(&self.search_entry, chunks[0]);
+                f.render_widget(&self.search_entry, chunks[1]);
 
                 // The emoji list.
-                f.render_widget(&mut filtered_view, chunks[1]);
-            })
-            .context("Failed to render widgets")?;
+                f.render_widget(&mut filtered_view, chunks[2]);
 
-        event::read()
-            .map(|event| match event {
-                Event::Key(key_event) => {
-                    Self::handle_key_event(key_event, &mut self.search_entry, &mut filtered_view)
+                // The variants of the selected emoji, on top of the list.
+                if let Some(variant_picker) = &self.variant_picker {
+                    f.render_widget(variant_picker, chunks[2]);
                 }
-                _ => EventResponse::Noop,
             })
-            .context("Failed to read UI event")
+            .context("Failed to render widgets")?;
+
+        let Event::Key(key_event) = event::read().context("Failed to read UI event")? else {
+            return Ok(EventResponse::Noop);
+        };
+
+        let action =
+            chord(&key_event).and_then(|(key, modifiers)| self.keys.action(key, modifiers));
+        if let Some(variant_picker) = &mut self.variant_picker {
+            let response = Self::handle_variant_key_event(key_event, action, variant_picker);
+            if action == Some(Action::Cancel) {
+                self.variant_picker = None;
+            }
+
+            return Ok(response);
+        }
+
+        if action == Some(Action::CycleSet) {
+            self.selection_view.cycle_set();
+            return Ok(EventResponse::Noop);
+        }
+
+        Ok(Self::handle_key_event(
+            key_event,
+            action,
+            &mut self.search_entry,
+            &mut self.selection_view.filtered_view(),
+            &mut self.variant_picker,
+            self.colors,
+        ))
     }
 
     fn handle_key_event(
         event: KeyEvent,
+        action: Option<Action>,
         search_entry: &mut SearchEntry,
         filtered_view: &mut FilteredView,
+        variant_picker: &mut Option<VariantPicker>,
+        colors: Colors,
     ) -> EventResponse {
-        match event.code {
-            KeyCode::Enter => filtered_view
-                .selected()
-                .map(|emoji| emoji.emoji)
-                .map(EventResponse::EmojiSelected)
-                .unwrap_or_default(),
-            KeyCode::Esc => {
+        match action {
+            Some(Action::Select) => match filtered_view.selected_variants() {
+                Some(variants) if variants.len() > 1 => {
+                    *variant_picker = Some(VariantPicker::new(colors, variants.into()));
+                    EventResponse::Noop
+                }
+                Some(variants) => EventResponse::EmojiSelected(variants[0]),
+                None => EventResponse::Noop,
+            },
+            Some(Action::Cancel) => {
                 if search_entry.text().is_empty() {
                     EventResponse::Exit
                 } else {
@@ -93,36 +222,355 @@ impl Terminal {
                     EventResponse::Noop
                 }
             }
-            KeyCode::Down | KeyCode::Tab => {
+            Some(Action::Exit) => EventResponse::Exit,
+            Some(Action::Down) => {
                 filtered_view.move_down();
                 EventResponse::Noop
             }
-            KeyCode::Up | KeyCode::BackTab => {
+            Some(Action::Up) => {
                 filtered_view.move_up();
                 EventResponse::Noop
             }
-            KeyCode::Char(ch) => {
-                if ch == 'c' && event.modifiers.contains(KeyModifiers::CONTROL) {
-                    EventResponse::Exit
-                } else {
+            Some(Action::DeleteChar) => {
+                search_entry.pop();
+                EventResponse::Noop
+            }
+            Some(Action::CycleMatchMode) => {
+                search_entry.cycle_mode();
+                EventResponse::Noop
+            }
+            // Handled by the caller.
+            Some(Action::CycleSet) => EventResponse::Noop,
+            None => {
+                if let KeyCode::Char(ch) = event.code {
                     search_entry.push(ch);
-                    EventResponse::Noop
                 }
+                EventResponse::Noop
             }
-            KeyCode::Backspace => {
-                search_entry.pop();
+        }
+    }
+
+    fn handle_variant_key_event(
+        event: KeyEvent,
+        action: Option<Action>,
+        variant_picker: &mut VariantPicker,
+    ) -> EventResponse {
+        match (action, event.code) {
+            (Some(Action::Select), _) => EventResponse::EmojiSelected(variant_picker.selected()),
+            (Some(Action::Down), _) | (_, KeyCode::Right) => {
+                variant_picker.move_right();
                 EventResponse::Noop
             }
+            (Some(Action::Up), _) | (_, KeyCode::Left) => {
+                variant_picker.move_left();
+                EventResponse::Noop
+            }
+            (Some(Action::Exit), _) => EventResponse::Exit,
             _ => EventResponse::Noop,
         }
     }
 
     pub fn reset(&mut self) -> anyhow::Result<()> {
+        if self.reset {
+            return Ok(());
+        }
+        self.reset = true;
         terminal::disable_raw_mode().context("Failed to disable raw mode")?;
-        execute!(self.term.backend_mut(), LeaveAlternateScreen)
-            .context("Failed to leave alternate screen")?;
+        match self.screen {
+            Screen::Full => execute!(self.term.backend_mut(), LeaveAlternateScreen)
+                .context("Failed to leave alternate screen")?,
+            Screen::Inline(_) => {
+                self.term.clear().context("Failed to clear the picker")?;
+                execute!(self.term.backend_mut(), RestorePosition)
+                    .context("Failed to restore cursor position")?
+            }
+        }
         self.term
             .show_cursor()
             .context("Failed to show terminal cursor")
     }
 }
+
+/// The terminal is left as it was found even when the picker fails or panics.
+#[cfg(feature = "tui")]
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // Too late to report.
+        let _ = self.reset();
+    }
+}
+
+/// Where to draw the picker: stdout, unless it's redirected, e.g. for the emoji to be printed in a
+/// command substitution, in which case it's drawn on the terminal directly.
+#[cfg(feature = "tui")]
+fn output() -> anyhow::Result<Box<dyn Write>> {
+    #[cfg(unix)]
+    if !io::stdout().is_terminal() {
+        let tty = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/tty")
+            .context("Failed to open the terminal")?;
+        return Ok(Box::new(tty));
+    }
+
+    Ok(Box::new(io::stdout()))
+}
+
+/// The key of the event and its modifiers, unless chords can't have them, e.g. media keys or
+/// `super`.
+#[cfg(feature = "tui")]
+fn chord(event: &KeyEvent) -> Option<(Key, Modifiers)> {
+    let key = match event.code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::F(n) => Key::F(n),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::BackTab => Key::BackTab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        _ => return None,
+    };
+    let held = event.modifiers;
+    let modifiers = Modifiers {
+        ctrl: held.contains(KeyModifiers::CONTROL),
+        alt: held.contains(KeyModifiers::ALT),
+        shift: held.contains(KeyModifiers::SHIFT),
+    };
+    let chordable = KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT;
+
+    chordable.contains(held).then_some((key, modifiers))
+}
+
+/// Let the user pick one of the emojis of the sets, starting with the selected one, with the
+/// interactive picker. `None` if they exit without picking one.
+///
+/// The search starts with the given query, selected so that typing replaces it, which is then set
+/// to the final one, e.g. for the next search to start with. The commit message the emoji is for,
+/// if any, is shown above it.
+///
+/// Fails with [`Error::NoTty`] if there's no terminal to show the picker on.
+#[cfg(feature = "tui")]
+#[allow(clippy::too_many_arguments)] // All of them are settings of the picker.
+pub fn select_emoji(
+    colors: Colors,
+    keys: Keymap,
+    search_options: SearchOptions,
+    sets: Box<[EmojiSet]>,
+    selected_set: usize,
+    screen: Screen,
+    query: &mut String,
+    message: Option<String>,
+) -> anyhow::Result<Option<&'static Emoji>> {
+    if !is_available() {
+        return Err(Error::NoTty.into());
+    }
+    let mut terminal = Terminal::new(
+        colors,
+        keys,
+        search_options,
+        sets,
+        selected_set,
+        screen,
+        std::mem::take(query),
+    )?;
+    terminal.message_pane = message.map(|message| MessagePane::new(colors, message));
+    let emoji = loop {
+        match terminal.render_ui()? {
+            EventResponse::Noop => {}
+            EventResponse::EmojiSelected(emoji) => break Some(emoji),
+            EventResponse::Exit => break None,
+        }
+    };
+    query.push_str(terminal.search_entry.text());
+
+    terminal.reset().map(|()| emoji)
+}
+
+/// Let the user pick an emoji for each of the commits, given as one-line summaries listed beside
+/// the picker, one after the other. The commits left when they exit get none.
+///
+/// Fails with [`Error::NoTty`] if there's no terminal to show the picker on.
+#[cfg(feature = "tui")]
+pub fn select_emojis(
+    colors: Colors,
+    keys: Keymap,
+    search_options: SearchOptions,
+    sets: Box<[EmojiSet]>,
+    selected_set: usize,
+    commits: Box<[String]>,
+) -> anyhow::Result<Vec<Option<&'static Emoji>>> {
+    if !is_available() {
+        return Err(Error::NoTty.into());
+    }
+    let mut terminal = Terminal::new(
+        colors,
+        keys,
+        search_options,
+        sets,
+        selected_set,
+        Screen::Full,
+        String::new(),
+    )?;
+    terminal.commit_list = Some(CommitList::new(colors, commits));
+    loop {
+        match terminal.render_ui()? {
+            EventResponse::Noop => {}
+            EventResponse::EmojiSelected(emoji) => {
+                terminal.variant_picker = None;
+                terminal.search_entry.clear();
+                let left = terminal
+                    .commit_list
+                    .as_mut()
+                    .is_some_and(|commit_list| commit_list.pick(emoji));
+                if !left {
+                    break;
+                }
+            }
+            EventResponse::Exit => break,
+        }
+    }
+    let emojis = terminal
+        .commit_list
+        .take()
+        .map(CommitList::into_emojis)
+        .unwrap_or_default();
+
+    terminal.reset().map(|()| emojis)
+}
+
+#[cfg(not(feature = "tui"))]
+#[allow(clippy::boxed_local)] // The same signature as with the picker.
+pub fn select_emojis(
+    _colors: Colors,
+    _keys: Keymap,
+    _search_options: SearchOptions,
+    _sets: Box<[EmojiSet]>,
+    _selected_set: usize,
+    _commits: Box<[String]>,
+) -> anyhow::Result<Vec<Option<&'static Emoji>>> {
+    Err(Error::NoTty.into())
+}
+
+#[cfg(not(feature = "tui"))]
+#[allow(clippy::boxed_local, clippy::too_many_arguments)] // The same signature as with the picker.
+pub fn select_emoji(
+    _colors: Colors,
+    _keys: Keymap,
+    _search_options: SearchOptions,
+    _sets: Box<[EmojiSet]>,
+    _selected_set: usize,
+    _screen: Screen,
+    _query: &mut String,
+    _message: Option<String>,
+) -> anyhow::Result<Option<&'static Emoji>> {
+    Err(Error::NoTty.into())
+}
+
+/// Copy the text to the terminal's clipboard, with an OSC 52 escape sequence.
+///
+/// Unlike the system clipboard, this works over SSH, as it's the local terminal that sets it.
+pub fn copy_to_clipboard(text: &str, selection: ClipboardSelection) -> anyhow::Result<()> {
+    let targets = match selection {
+        ClipboardSelection::Clipboard => "c",
+        ClipboardSelection::Primary => "p",
+        ClipboardSelection::Both => "pc",
+    };
+    let sequence = format!("\x1b]52;{targets};{}\x07", BASE64_STANDARD.encode(text));
+    // Straight to the terminal, in case stdout is redirected.
+    #[cfg(unix)]
+    let mut out = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context("Failed to open the terminal")?;
+    #[cfg(not(unix))]
+    let mut out = std::io::stdout();
+
+    out.write_all(sequence.as_bytes())
+        .and_then(|()| out.flush())
+        .context("Failed to copy emoji to the terminal's clipboard")
+}
+
+/// Whether there's a terminal to show the picker on.
+///
+/// Git runs hooks without one when committing from an IDE or a script, for example. Never the case
+/// without the `tui` feature, as there's no picker to show.
+pub fn is_available() -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(not(feature = "tui"))] {
+            false
+        } else if #[cfg(unix)] {
+            use std::io::IsTerminal;
+
+            // Like crossterm, which falls back to the controlling terminal when stdin isn't one.
+            std::io::stdin().is_terminal()
+                || std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open("/dev/tty")
+                    .is_ok()
+        } else {
+            use std::io::IsTerminal;
+
+            std::io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Ask the question on the terminal, returning the trimmed answer, or the default if there's none.
+///
+/// Asked on the terminal directly rather than stdin, which git hooks don't always have.
+pub fn ask(question: &str, default: &str) -> anyhow::Result<String> {
+    use std::io::BufRead;
+
+    #[cfg(unix)]
+    let (mut input, mut output): (Box<dyn BufRead>, Box<dyn Write>) = {
+        use std::io::IsTerminal;
+
+        // As is the case with the daemon, which has no controlling terminal.
+        if std::io::stdin().is_terminal() {
+            (
+                Box::new(std::io::stdin().lock()),
+                Box::new(std::io::stderr()),
+            )
+        } else {
+            let tty = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")
+                .map_err(|_| Error::NoTty)?;
+            let output = tty.try_clone().context("Failed to open the terminal")?;
+            (Box::new(std::io::BufReader::new(tty)), Box::new(output))
+        }
+    };
+    #[cfg(not(unix))]
+    let (mut input, mut output) = (std::io::stdin().lock(), std::io::stderr());
+
+    match default {
+        "" => write!(output, "? {question} "),
+        default => write!(output, "? {question} ({default}) "),
+    }
+    .and_then(|()| output.flush())
+    .context("Failed to write to the terminal")?;
+    let mut answer = String::new();
+    let read = input
+        .read_line(&mut answer)
+        .context("Failed to read from the terminal")?;
+    // Ctrl+D.
+    if read == 0 {
+        return Err(Error::Cancelled.into());
+    }
+
+    Ok(match answer.trim() {
+        "" => default.to_owned(),
+        answer => answer.to_owned(),
+    })
+}
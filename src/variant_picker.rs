@@ -0,0 +1,95 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::{colors::Colors, emoji::Emoji};
+
+/// A popup to pick one of the (skin tone) variants of an emoji.
+pub struct VariantPicker {
+    variants: Box<[&'static Emoji]>,
+    selected: usize,
+    colors: Colors,
+}
+
+impl VariantPicker {
+    /// The variants are expected to be non-empty, starting with the base emoji.
+    pub fn new(colors: Colors, variants: Box<[&'static Emoji]>) -> Self {
+        Self {
+            variants,
+            selected: 0,
+            colors,
+        }
+    }
+
+    pub fn selected(&self) -> &'static Emoji {
+        self.variants[self.selected]
+    }
+
+    pub fn move_left(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or(self.variants.len() - 1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.selected = (self.selected + 1) % self.variants.len();
+    }
+}
+
+impl Widget for &VariantPicker {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Borders and padding take 4 columns and 2 rows.
+        let per_row = (MAX_WIDTH.min(area.width).saturating_sub(4) / CELL_WIDTH).max(1) as usize;
+        let rows = self.variants.len().div_ceil(per_row);
+        let width = (per_row.min(self.variants.len()) as u16 * CELL_WIDTH + 4).min(area.width);
+        let height = (rows as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let lines: Vec<_> = self
+            .variants
+            .chunks(per_row)
+            .enumerate()
+            .map(|(row, variants)| {
+                let spans = variants.iter().enumerate().map(|(col, emoji)| {
+                    let style = if row * per_row + col == self.selected {
+                        Style::default()
+                            .add_modifier(Modifier::REVERSED)
+                            .fg(self.colors.selected)
+                    } else {
+                        Style::default()
+                    };
+
+                    Span::styled(format!("{} ", emoji.emoji), style)
+                });
+
+                Line::from(spans.collect::<Vec<_>>())
+            })
+            .collect();
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(TITLE)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.colors.border))
+                    .padding(Padding::horizontal(1)),
+            )
+            .render(popup, buf);
+    }
+}
+
+const TITLE: &str = "Select a variant";
+const MAX_WIDTH: u16 = 64;
+// An emoji is usually 2 columns wide, plus a space.
+const CELL_WIDTH: u16 = 3;